@@ -0,0 +1,4 @@
+// Rebuild when a migration is added or changed, since they are embedded with `sqlx::migrate!`.
+fn main() {
+    println!("cargo:rerun-if-changed=src/database/migrations");
+}
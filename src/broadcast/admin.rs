@@ -1,15 +1,15 @@
 use poise::serenity_prelude as serenity;
-use serenity::{CacheHttp, CreateAttachment, CreateEmbed};
+use serenity::CacheHttp;
 
 use crate::broadcast::broadcast_handler::BroadcastType;
+use crate::database::controllers::badactor_model_controller::BroadcastEmbed;
 use crate::util::config::Config;
 
 use super::broadcast_handler::get_broadcast_message_no_buttons;
 
 pub struct BroadcastAdminServerOptions<'a> {
     pub config: &'a Config,
-    pub embed: CreateEmbed,
-    pub attachment: Option<CreateAttachment>,
+    pub embed: BroadcastEmbed,
     pub broadcast_type: BroadcastType,
 }
 
@@ -20,11 +20,10 @@ pub async fn broadcast_admin_server(
     let BroadcastAdminServerOptions {
         config,
         embed,
-        attachment,
         broadcast_type,
     } = options;
 
-    let message = get_broadcast_message_no_buttons(broadcast_type.message(), embed, attachment);
+    let message = get_broadcast_message_no_buttons(broadcast_type.message(), embed);
 
     config
         .admin_server_log_channel
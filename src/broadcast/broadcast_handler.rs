@@ -1,20 +1,21 @@
 use poise::serenity_prelude as serenity;
 use serenity::{
-    ButtonStyle, CacheHttp, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
-    CreateMessage, GuildId, PartialGuild, User, UserId,
+    ButtonStyle, CacheHttp, CreateActionRow, CreateButton, CreateMessage, GuildId, PartialGuild,
+    User, UserId,
 };
 use sqlx::PgPool;
 
-use crate::database::controllers::badactor_model_controller::{BadActor, BroadcastEmbedOptions};
+use crate::database::controllers::badactor_model_controller::{
+    BadActor, BroadcastEmbed, BroadcastEmbedOptions,
+};
+use crate::database::controllers::dmtemplate_model_controller::DmTemplateModelController;
 use crate::database::controllers::serverconfig_model_controller::ActionLevel;
+use crate::database::controllers::webhook_model_controller::WebhookModelController;
+use crate::moderation::interaction::{ModerationButtonId, ModerationCustomId};
 use crate::util::embeds::EmbedColor;
 use crate::util::{config, format, logger};
 
-use super::listener::BroadcastListener;
-use super::moderate::ModerateOptions;
-use super::send::SendBroadcastMessageOptions;
-use super::webhooks::BroadcastWebhookOptions;
-use super::{admin, listener, moderate, send, webhooks};
+use super::{admin, listener, outbox};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BroadcastType {
@@ -22,11 +23,61 @@ pub enum BroadcastType {
     Deactivate,
     AddScreenshot,
     ReplaceScreenshot,
+    AddEvidence,
+    RemoveEvidence,
     UpdateExplanation,
     Honeypot,
+    Reactivate,
+}
+
+impl std::fmt::Display for BroadcastType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Report => write!(f, "report"),
+            Self::Deactivate => write!(f, "deactivate"),
+            Self::AddScreenshot => write!(f, "add_screenshot"),
+            Self::ReplaceScreenshot => write!(f, "replace_screenshot"),
+            Self::AddEvidence => write!(f, "add_evidence"),
+            Self::RemoveEvidence => write!(f, "remove_evidence"),
+            Self::UpdateExplanation => write!(f, "update_explanation"),
+            Self::Honeypot => write!(f, "honeypot"),
+            Self::Reactivate => write!(f, "reactivate"),
+        }
+    }
+}
+
+impl std::str::FromStr for BroadcastType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "report" => Ok(Self::Report),
+            "deactivate" => Ok(Self::Deactivate),
+            "add_screenshot" => Ok(Self::AddScreenshot),
+            "replace_screenshot" => Ok(Self::ReplaceScreenshot),
+            "add_evidence" => Ok(Self::AddEvidence),
+            "remove_evidence" => Ok(Self::RemoveEvidence),
+            "update_explanation" => Ok(Self::UpdateExplanation),
+            "honeypot" => Ok(Self::Honeypot),
+            "reactivate" => Ok(Self::Reactivate),
+            _ => anyhow::bail!("Invalid broadcast type: {}", s),
+        }
+    }
 }
 
 impl BroadcastType {
+    pub const ALL: [Self; 9] = [
+        Self::Report,
+        Self::Deactivate,
+        Self::AddScreenshot,
+        Self::ReplaceScreenshot,
+        Self::AddEvidence,
+        Self::RemoveEvidence,
+        Self::UpdateExplanation,
+        Self::Honeypot,
+        Self::Reactivate,
+    ];
+
     pub fn message(&self) -> &'static str {
         match self {
             Self::Report => "A bad actor has been reported.",
@@ -34,12 +85,22 @@ impl BroadcastType {
             Self::AddScreenshot => "A screenshot proof has been added to a bad actor entry.",
             Self::UpdateExplanation => "The explanation for a bad actor has been updated.",
             Self::ReplaceScreenshot => "A screenshot has been replaced for a bad actor.",
+            Self::AddEvidence => "Evidence has been added to a bad actor entry.",
+            Self::RemoveEvidence => "Evidence has been removed from a bad actor entry.",
             Self::Honeypot => "A bad actor was caught by the honeypot.",
+            Self::Reactivate => "A bad actor has been reactivated.",
         }
     }
 
+    /// Reactivated reports count as new reports, so listeners take their configured actions again.
     pub fn is_new_report(&self) -> bool {
-        matches!(self, Self::Report | Self::Honeypot)
+        matches!(self, Self::Report | Self::Honeypot | Self::Reactivate)
+    }
+
+    /// Whether a broadcast of this type is outdated once the report's active state changed.
+    /// New reports only apply to active reports and deactivations only to inactive ones, the other types are informational.
+    pub fn is_obsolete_for(&self, is_active: bool) -> bool {
+        (self.is_new_report() && !is_active) || (*self == Self::Deactivate && is_active)
     }
 }
 
@@ -56,16 +117,6 @@ pub struct BroadcastOptions<'a> {
     pub broadcast_type: BroadcastType,
 }
 
-struct BroadcastToListenersOptions<'a> {
-    db_pool: &'a PgPool,
-    broadcast_type: BroadcastType,
-    listeners: &'a [BroadcastListener],
-    bad_actor: &'a BadActor,
-    target_user: &'a User,
-    embed: CreateEmbed,
-    attachment: Option<CreateAttachment>,
-}
-
 pub async fn broadcast(cache_http: impl CacheHttp, options: BroadcastOptions<'_>) {
     let BroadcastOptions {
         config,
@@ -88,23 +139,28 @@ pub async fn broadcast(cache_http: impl CacheHttp, options: BroadcastOptions<'_>
         }
     };
 
+    let display_origin = origin_guild
+        .as_ref()
+        .map(|g| g.name.clone())
+        .unwrap_or(origin_guild_id.to_string());
+
     let embed_options = BroadcastEmbedOptions {
         origin_guild_id,
         origin_guild,
         report_author: reporting_user,
         bot_id: reporting_bot_id,
+        db_pool,
     };
 
     let embed_colour = get_embed_colour(broadcast_type);
 
-    let (embed, attachment) = bad_actor
+    let embed = bad_actor
         .to_broadcast_embed(&cache_http, embed_options, embed_colour)
         .await;
 
     let admin_options = admin::BroadcastAdminServerOptions {
         config,
         embed: embed.clone(),
-        attachment: attachment.clone(),
         broadcast_type,
     };
 
@@ -113,96 +169,103 @@ pub async fn broadcast(cache_http: impl CacheHttp, options: BroadcastOptions<'_>
         logger::Logger::get().error(&cache_http, e, log_msg).await;
     }
 
-    if broadcast_type.is_new_report() && notify_user(&cache_http, bad_actor_user).await.is_err() {
-        let log_msg = format!(
-            "Failed to inform {} about the moderation actions in DM",
-            format::display(bad_actor_user)
-        );
-        logger::Logger::get().warn(&cache_http, log_msg).await;
+    if broadcast_type.is_new_report() {
+        let notify_options = NotifyUserOptions {
+            db_pool,
+            target_user: bad_actor_user,
+            bad_actor,
+            display_origin: &display_origin,
+        };
+
+        if let Err(e) = notify_user(&cache_http, notify_options).await {
+            let log_msg = format!(
+                "Failed to inform {} about the moderation actions in DM: {e}",
+                format::display(bad_actor_user)
+            );
+            logger::Logger::get().warn(&cache_http, log_msg).await;
+        }
     }
 
-    let listener_options = BroadcastToListenersOptions {
+    let webhooks = match WebhookModelController::get_all(db_pool).await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            let log_msg = "Failed to get webhooks to broadcast to from the database";
+            logger::Logger::get().error(&cache_http, e, log_msg).await;
+            Vec::new()
+        }
+    };
+
+    let enqueue_options = outbox::EnqueueOptions {
         db_pool,
-        broadcast_type,
         listeners: &listeners,
+        webhooks: &webhooks,
         bad_actor,
-        target_user: bad_actor_user,
-        embed,
-        attachment,
+        broadcast_type,
+        reporting_user_id: reporting_user.id,
+        origin_guild_id,
     };
 
-    broadcast_to_listeners(&cache_http, listener_options).await;
-}
+    let deliveries = match outbox::enqueue(enqueue_options).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            let log_msg = "Failed to add broadcast deliveries to the outbox";
+            logger::Logger::get().error(&cache_http, e, log_msg).await;
+            return;
+        }
+    };
 
-async fn broadcast_to_listeners(
-    cache_http: impl CacheHttp,
-    options: BroadcastToListenersOptions<'_>,
-) {
-    let BroadcastToListenersOptions {
-        db_pool,
+    let content = outbox::DeliveryContent {
         broadcast_type,
-        listeners,
         bad_actor,
-        target_user,
-        embed,
-        attachment,
-    } = options;
-
-    let futures = listeners.iter().map(|listener| async {
-        let send_options = SendBroadcastMessageOptions {
-            broadcast_type,
-            listener,
-            bad_actor,
-            embed: &embed,
-            attachment: &attachment,
-        };
-
-        let moderate_options = ModerateOptions {
-            broadcast_type,
-            listener,
-            bad_actor,
-            target_user,
-        };
-
-        let webhooks_options = BroadcastWebhookOptions {
-            db_pool,
-            broadcast_type,
-            embed: &embed,
-            attachment: &attachment,
-        };
-
-        tokio::join!(
-            send::send_broadcast_message(&cache_http, send_options),
-            moderate::moderate(&cache_http, moderate_options),
-            webhooks::broadcast_to_webhooks(&cache_http, webhooks_options)
-        );
-    });
+        target_user: bad_actor_user,
+        embed: &embed,
+    };
 
-    futures::future::join_all(futures).await;
+    outbox::deliver_all(
+        &cache_http,
+        db_pool,
+        &deliveries,
+        &listeners,
+        &webhooks,
+        &content,
+    )
+    .await;
 }
 
 pub fn get_broadcast_message(
     content: &str,
-    embed: CreateEmbed,
-    attachment: Option<CreateAttachment>,
+    bad_actor: &BadActor,
+    embed: BroadcastEmbed,
     action_level: ActionLevel,
     broadcast_type: BroadcastType,
 ) -> CreateMessage {
+    // the buttons carry the report, so moderating does not depend on the embed's layout
+    let button = |action: ModerationCustomId| {
+        let button_id = ModerationButtonId {
+            action,
+            report_id: bad_actor.id,
+            target_user_id: bad_actor.user_id,
+        };
+
+        CreateButton::new(button_id.to_string())
+    };
+
     let mut buttons = Vec::new();
 
     if broadcast_type.is_new_report() && action_level == ActionLevel::Notify {
-        buttons.push(CreateButton::new("ban").label("Ban"));
-        buttons.push(CreateButton::new("softban").label("Softban"));
-        buttons.push(CreateButton::new("kick").label("Kick"));
+        buttons.push(button(ModerationCustomId::Ban).label("Ban"));
+        buttons.push(button(ModerationCustomId::SoftBan).label("Softban"));
+        buttons.push(button(ModerationCustomId::Kick).label("Kick"));
+        buttons.push(button(ModerationCustomId::Timeout).label("Timeout"));
         buttons.push(
-            CreateButton::new("no_action")
+            button(ModerationCustomId::NoAction)
                 .label("No Action")
                 .style(ButtonStyle::Danger),
         )
     } else if broadcast_type == BroadcastType::Deactivate {
-        buttons.push(CreateButton::new("unban").label("Unban"));
+        buttons.push(button(ModerationCustomId::Unban).label("Unban"));
         buttons.push(
-            CreateButton::new("no_action")
+            button(ModerationCustomId::NoAction)
                 .label("No Action")
                 .style(ButtonStyle::Danger),
         )
@@ -211,14 +274,11 @@ pub fn get_broadcast_message(
     let button_len = buttons.len();
     let action_row = CreateActionRow::Buttons(buttons);
 
-    let message = CreateMessage::new().content(content).embed(embed);
-
-    // add the screenshot to the embed
-    let message = if let Some(attachment) = attachment {
-        message.add_file(attachment)
-    } else {
-        message
-    };
+    // add the screenshots and other evidence to the message
+    let message = CreateMessage::new()
+        .content(content)
+        .embeds(embed.embeds())
+        .add_files(embed.attachments);
 
     // add the buttons to the embed and return the message
     if button_len > 0 {
@@ -228,23 +288,39 @@ pub fn get_broadcast_message(
     }
 }
 
-pub fn get_broadcast_message_no_buttons(
-    content: &str,
-    embed: CreateEmbed,
-    attachment: Option<CreateAttachment>,
-) -> CreateMessage {
-    if let Some(attachment) = attachment {
-        CreateMessage::new()
-            .content(content)
-            .embed(embed)
-            .add_file(attachment)
-    } else {
-        CreateMessage::new().content(content).embed(embed)
-    }
+pub fn get_broadcast_message_no_buttons(content: &str, embed: BroadcastEmbed) -> CreateMessage {
+    CreateMessage::new()
+        .content(content)
+        .embeds(embed.embeds())
+        .add_files(embed.attachments)
 }
 
-async fn notify_user(cache_http: impl CacheHttp, target_user: &User) -> anyhow::Result<()> {
-    let content = "It appears your account has been compromised and used as a spam bot.\n\nAs part of a collaborative effort to more efficiently moderate TMC servers, the actions as listed in the embed have been taken against your account.\nSince not all guilds have automatic moderation, it's possible that you have been banned from more servers than listed.\n\nIf you have now recovered your account, please join this server (https://discord.gg/7tp82FGk3n).\nFollow the instructions there to clear your name and remove the bans on your account.";
+struct NotifyUserOptions<'a> {
+    db_pool: &'a PgPool,
+    target_user: &'a User,
+    bad_actor: &'a BadActor,
+    display_origin: &'a str,
+}
+
+/// Sends the reported user the DM template for their bad actor type, unless admins disabled it.
+async fn notify_user(
+    cache_http: impl CacheHttp,
+    options: NotifyUserOptions<'_>,
+) -> anyhow::Result<()> {
+    let NotifyUserOptions {
+        db_pool,
+        target_user,
+        bad_actor,
+        display_origin,
+    } = options;
+
+    let template = DmTemplateModelController::get(db_pool, bad_actor.actor_type).await?;
+
+    if !template.enabled {
+        return Ok(());
+    }
+
+    let content = template.render(bad_actor.id, display_origin);
 
     target_user
         .direct_message(cache_http, CreateMessage::new().content(content))
@@ -253,13 +329,35 @@ async fn notify_user(cache_http: impl CacheHttp, target_user: &User) -> anyhow::
     Ok(())
 }
 
-fn get_embed_colour(broadcast_type: BroadcastType) -> EmbedColor {
+pub fn get_embed_colour(broadcast_type: BroadcastType) -> EmbedColor {
     match broadcast_type {
         BroadcastType::AddScreenshot => EmbedColor::Yellow,
         BroadcastType::Deactivate => EmbedColor::Green,
         BroadcastType::Honeypot => EmbedColor::DeepPink,
         BroadcastType::Report => EmbedColor::Red,
         BroadcastType::ReplaceScreenshot => EmbedColor::Orange,
+        BroadcastType::AddEvidence => EmbedColor::Yellow,
+        BroadcastType::RemoveEvidence => EmbedColor::Orange,
         BroadcastType::UpdateExplanation => EmbedColor::Orange,
+        BroadcastType::Reactivate => EmbedColor::Coral,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moderation_broadcasts_become_obsolete_when_the_report_flips() {
+        assert!(!BroadcastType::Report.is_obsolete_for(true));
+        assert!(BroadcastType::Report.is_obsolete_for(false));
+        assert!(BroadcastType::Honeypot.is_obsolete_for(false));
+        assert!(BroadcastType::Reactivate.is_obsolete_for(false));
+
+        assert!(!BroadcastType::Deactivate.is_obsolete_for(false));
+        assert!(BroadcastType::Deactivate.is_obsolete_for(true));
+
+        assert!(!BroadcastType::UpdateExplanation.is_obsolete_for(false));
+        assert!(!BroadcastType::AddEvidence.is_obsolete_for(true));
     }
 }
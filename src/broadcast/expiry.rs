@@ -0,0 +1,94 @@
+use poise::serenity_prelude as serenity;
+use serenity::UserId;
+use sqlx::PgPool;
+
+use crate::database::controllers::badactor_model_controller::{BadActor, BadActorModelController};
+use crate::util::config::Config;
+use crate::util::{format, locks, logger::Logger};
+
+use super::broadcast_handler::{self, BroadcastOptions, BroadcastType};
+
+/// Deactivates all reports whose expiry has passed.
+pub async fn expire_due(
+    ctx: &serenity::Context,
+    db_pool: &PgPool,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let expired = BadActorModelController::get_expired(db_pool).await?;
+    let bot_id = ctx.cache.current_user().id;
+
+    for bad_actor in expired {
+        if let Err(e) = expire(ctx, db_pool, config, bot_id, &bad_actor).await {
+            let log_msg = format!("Failed to deactivate expired report {}", bad_actor.id);
+            Logger::get().error(ctx, e, log_msg).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Deactivates the report with the bot as the last editor and broadcasts the deactivation.
+async fn expire(
+    ctx: &serenity::Context,
+    db_pool: &PgPool,
+    config: &Config,
+    bot_id: UserId,
+    bad_actor: &BadActor,
+) -> anyhow::Result<()> {
+    let _guard = locks::lock_user_id(bad_actor.user_id).await;
+
+    // the report might have been deactivated or reactivated since it was fetched
+    let Some(current) = BadActorModelController::get_by_id(db_pool, bad_actor.id).await? else {
+        return Ok(());
+    };
+
+    if !current.is_active || current.expires_at != bad_actor.expires_at {
+        return Ok(());
+    }
+
+    let deactivated = BadActorModelController::deavtivate(
+        db_pool,
+        bad_actor.id,
+        "The report expired.",
+        bot_id,
+        bad_actor.origin_guild_id,
+    )
+    .await?;
+
+    tracing::info!("Deactivated expired report {}.", deactivated.id);
+
+    let Some(target_user) = deactivated.user(ctx).await else {
+        let log_msg = format!(
+            "User with ID {} does not exist anymore, skipping broadcast of expired report {}",
+            deactivated.user_id, deactivated.id
+        );
+        Logger::get().warn(ctx, log_msg).await;
+        return Ok(());
+    };
+
+    let bot_user = bot_id.to_user(ctx).await?;
+
+    let origin_guild = deactivated.origin_guild_id.to_partial_guild(ctx).await.ok();
+
+    let broadcast_options = BroadcastOptions {
+        config,
+        db_pool,
+        reporting_user: &bot_user,
+        reporting_bot_id: bot_id,
+        bad_actor: &deactivated,
+        bad_actor_user: &target_user,
+        origin_guild,
+        origin_guild_id: deactivated.origin_guild_id,
+        broadcast_type: BroadcastType::Deactivate,
+    };
+
+    broadcast_handler::broadcast(ctx, broadcast_options).await;
+
+    tracing::info!(
+        "Broadcasted the expiry of report {} for {}.",
+        deactivated.id,
+        format::display(&target_user)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::database::controllers::badactor_model_controller::BadActorType;
+
+use super::broadcast_handler::BroadcastType;
+
+/// Which broadcasts a log channel or webhook receives. An empty list receives every type.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BroadcastFilter {
+    pub actor_types: Vec<BadActorType>,
+    pub broadcast_types: Vec<BroadcastType>,
+}
+
+impl BroadcastFilter {
+    pub fn try_from_db(actor_types: &[String], broadcast_types: &[String]) -> anyhow::Result<Self> {
+        let actor_types = actor_types
+            .iter()
+            .map(|t| BadActorType::from_str(t))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let broadcast_types = broadcast_types
+            .iter()
+            .map(|t| BroadcastType::from_str(t))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            actor_types,
+            broadcast_types,
+        })
+    }
+
+    pub fn actor_types_to_db(&self) -> Vec<String> {
+        self.actor_types.iter().map(|t| t.to_string()).collect()
+    }
+
+    pub fn broadcast_types_to_db(&self) -> Vec<String> {
+        self.broadcast_types.iter().map(|t| t.to_string()).collect()
+    }
+
+    pub fn matches(&self, actor_type: BadActorType, broadcast_type: BroadcastType) -> bool {
+        let actor_matches = self.actor_types.is_empty() || self.actor_types.contains(&actor_type);
+        let broadcast_matches =
+            self.broadcast_types.is_empty() || self.broadcast_types.contains(&broadcast_type);
+
+        actor_matches && broadcast_matches
+    }
+}
+
+impl Display for BroadcastFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Bad Actor Types: {}\nBroadcast Types: {}",
+            display_types(&self.actor_types),
+            display_types(&self.broadcast_types)
+        )
+    }
+}
+
+fn display_types<T: Display>(types: &[T]) -> String {
+    if types.is_empty() {
+        return String::from("all");
+    }
+
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = BroadcastFilter::default();
+
+        assert!(filter.matches(BadActorType::Spam, BroadcastType::Report));
+        assert!(filter.matches(BadActorType::Bigotry, BroadcastType::UpdateExplanation));
+    }
+
+    #[test]
+    fn filter_requires_both_types_to_match() {
+        let filter = BroadcastFilter {
+            actor_types: vec![BadActorType::Spam],
+            broadcast_types: vec![BroadcastType::Report, BroadcastType::Honeypot],
+        };
+
+        assert!(filter.matches(BadActorType::Spam, BroadcastType::Report));
+        assert!(filter.matches(BadActorType::Spam, BroadcastType::Honeypot));
+        assert!(!filter.matches(BadActorType::Spam, BroadcastType::ReplaceScreenshot));
+        assert!(!filter.matches(BadActorType::Impersonation, BroadcastType::Report));
+    }
+
+    #[test]
+    fn round_trips_through_db_strings() {
+        let filter = BroadcastFilter {
+            actor_types: vec![BadActorType::Honeypot, BadActorType::Bigotry],
+            broadcast_types: vec![BroadcastType::Deactivate],
+        };
+
+        let from_db = BroadcastFilter::try_from_db(
+            &filter.actor_types_to_db(),
+            &filter.broadcast_types_to_db(),
+        )
+        .unwrap();
+
+        assert_eq!(from_db, filter);
+    }
+
+    #[test]
+    fn every_type_parses_from_its_db_string() {
+        for actor_type in BadActorType::ALL {
+            assert_eq!(
+                BadActorType::from_str(&actor_type.to_string()).unwrap(),
+                actor_type
+            );
+        }
+
+        for broadcast_type in BroadcastType::ALL {
+            assert_eq!(
+                BroadcastType::from_str(&broadcast_type.to_string()).unwrap(),
+                broadcast_type
+            );
+        }
+    }
+}
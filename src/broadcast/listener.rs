@@ -52,6 +52,31 @@ pub async fn get_valid_listeners(
     Ok(valid_configs)
 }
 
+/// Gets a single listener by its [GuildId], failing if it does not have a valid log channel.
+pub async fn get_valid_listener(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+    guild_id: GuildId,
+) -> anyhow::Result<BroadcastListener> {
+    let Some(server_config) =
+        ServerConfigModelController::get_by_guild_id(db_pool, guild_id).await?
+    else {
+        anyhow::bail!("There is no server config for {guild_id}");
+    };
+
+    let (_, config, log_channel) = get_valid_logchannel(server_config, &cache_http, db_pool).await;
+    let config = config?;
+
+    let Some(log_channel) = log_channel else {
+        anyhow::bail!("There is no valid log channel for {guild_id}");
+    };
+
+    Ok(BroadcastListener {
+        config,
+        log_channel,
+    })
+}
+
 async fn get_valid_logchannel(
     server_config: ServerConfig,
     cache_http: impl CacheHttp,
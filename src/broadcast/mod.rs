@@ -1,6 +1,9 @@
 pub mod admin;
 pub mod broadcast_handler;
+pub mod expiry;
+pub mod filter;
 pub mod listener;
 pub mod moderate;
+pub mod outbox;
 pub mod send;
 pub mod webhooks;
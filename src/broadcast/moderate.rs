@@ -1,11 +1,16 @@
-use chrono::{Days, Utc};
+use anyhow::Context;
+use chrono::{Duration, Utc};
 use poise::serenity_prelude as serenity;
 use serenity::{
-    CacheHttp, CreateMessage, GuildChannel, GuildId, Member, Mentionable, PartialGuild, RoleId,
-    User,
+    CacheHttp, CreateMessage, GuildChannel, GuildId, HttpError, Member, Mentionable, PartialGuild,
+    RoleId, User,
 };
+use sqlx::PgPool;
 
 use crate::database::controllers::badactor_model_controller::{BadActor, BadActorType};
+use crate::database::controllers::moderationaction_model_controller::{
+    CreateModerationAction, ModerationActionModelController, ModerationActionType, ModerationSource,
+};
 use crate::database::controllers::serverconfig_model_controller::{ActionLevel, ServerConfig};
 use crate::util::format;
 use crate::util::logger::Logger;
@@ -14,29 +19,40 @@ use super::broadcast_handler::BroadcastType;
 use super::listener::BroadcastListener;
 
 pub struct ModerateOptions<'a> {
+    pub db_pool: &'a PgPool,
     pub broadcast_type: BroadcastType,
     pub listener: &'a BroadcastListener,
     pub bad_actor: &'a BadActor,
     pub target_user: &'a User,
 }
 
-pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>) {
+/// Takes the automatic moderation action the listener configured for the bad actor.
+/// Returns an error if the moderation action itself failed.
+pub async fn moderate(
+    cache_http: impl CacheHttp,
+    options: ModerateOptions<'_>,
+) -> anyhow::Result<()> {
     let ModerateOptions {
+        db_pool,
         broadcast_type,
         listener,
         bad_actor,
         target_user,
     } = options;
 
+    if broadcast_type == BroadcastType::Deactivate {
+        return auto_unban(&cache_http, db_pool, listener, bad_actor, target_user).await;
+    }
+
     let action_level = get_moderation_action(
         broadcast_type,
         bad_actor.actor_type,
         &listener.config.server_config,
     );
 
-    if let ActionLevel::Notify = action_level {
-        return;
-    }
+    let Some(action) = ModerationActionType::from_action_level(action_level) else {
+        return Ok(());
+    };
 
     let member = listener
         .config
@@ -47,16 +63,27 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
 
     // the only moderation action we can take on people who are not members it to ban them
     if member.is_none() && action_level == ActionLevel::Ban {
-        let _ban_result = ban(
+        let ban_result = ban(
             &cache_http,
             &listener.config.guild,
             target_user,
             &listener.log_channel,
             bad_actor.ban_reason(listener.config.server_config.ban_reason.clone()),
+            listener.config.server_config.ban_delete_message_days,
         )
         .await;
 
-        return;
+        record_action(
+            &cache_http,
+            db_pool,
+            listener,
+            bad_actor,
+            action,
+            &ban_result,
+        )
+        .await;
+
+        return with_moderation_context(ban_result, target_user, &listener.config.guild);
     }
 
     // inform the guild that the user is not a member
@@ -79,7 +106,7 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
             Logger::get().error(&cache_http, e, log_msg).await;
         }
 
-        return;
+        return Ok(());
     };
 
     let non_ignored_roles = get_non_ignored_roles(
@@ -98,7 +125,7 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
         )
         .await;
 
-        return;
+        return Ok(());
     }
 
     let moderation_result = match action_level {
@@ -109,6 +136,10 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
                 &listener.config.guild,
                 &mut member,
                 &listener.log_channel,
+                listener
+                    .config
+                    .server_config
+                    .action_timeout(bad_actor.actor_type),
             )
             .await
         }
@@ -127,6 +158,7 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
                 &listener.config.guild,
                 target_user,
                 &listener.log_channel,
+                listener.config.server_config.ban_delete_message_days,
             )
             .await
         }
@@ -137,35 +169,113 @@ pub async fn moderate(cache_http: impl CacheHttp, options: ModerateOptions<'_>)
                 target_user,
                 &listener.log_channel,
                 bad_actor.ban_reason(listener.config.server_config.ban_reason.clone()),
+                listener.config.server_config.ban_delete_message_days,
             )
             .await
         }
     };
 
-    log_moderation_result(
+    record_action(
         &cache_http,
-        moderation_result,
-        target_user,
-        &listener.config.guild,
+        db_pool,
+        listener,
+        bad_actor,
+        action,
+        &moderation_result,
     )
     .await;
+
+    with_moderation_context(moderation_result, target_user, &listener.config.guild)
 }
 
-async fn log_moderation_result(
+/// Lifts the ban of a deactivated bad actor if the listener opted into it and Janitor applied the ban.
+async fn auto_unban(
     cache_http: impl CacheHttp,
-    result: anyhow::Result<()>,
+    db_pool: &PgPool,
+    listener: &BroadcastListener,
+    bad_actor: &BadActor,
     target_user: &User,
-    guild: &PartialGuild,
+) -> anyhow::Result<()> {
+    if !listener.config.server_config.auto_unban_on_deactivate {
+        return Ok(());
+    }
+
+    let guild = &listener.config.guild;
+
+    if !ModerationActionModelController::has_janitor_ban(db_pool, guild.id, bad_actor.id).await? {
+        return Ok(());
+    }
+
+    let unban_result = unban(&cache_http, guild, target_user, &listener.log_channel).await;
+
+    record_action(
+        &cache_http,
+        db_pool,
+        listener,
+        bad_actor,
+        ModerationActionType::Unban,
+        &unban_result,
+    )
+    .await;
+
+    with_moderation_context(unban_result, target_user, guild)
+}
+
+/// Whether the listener takes any moderation action for the broadcast.
+pub fn takes_action(
+    broadcast_type: BroadcastType,
+    actor_type: BadActorType,
+    server_config: &ServerConfig,
+) -> bool {
+    if broadcast_type == BroadcastType::Deactivate {
+        return server_config.auto_unban_on_deactivate;
+    }
+
+    get_moderation_action(broadcast_type, actor_type, server_config) != ActionLevel::Notify
+}
+
+/// Records the automatic moderation action in the `moderation_actions` table.
+/// Failing to record it is only logged, the moderation action itself already happened.
+async fn record_action(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+    listener: &BroadcastListener,
+    bad_actor: &BadActor,
+    action: ModerationActionType,
+    result: &anyhow::Result<()>,
 ) {
-    if let Err(e) = result {
+    let create_action = CreateModerationAction {
+        guild_id: listener.config.guild.id,
+        target_user_id: bad_actor.user_id,
+        bad_actor_id: Some(bad_actor.id),
+        action,
+        source: ModerationSource::Automatic,
+        acting_user_id: None,
+        result,
+    };
+
+    if let Err(e) = ModerationActionModelController::create(db_pool, create_action).await {
         let log_msg = format!(
+            "Failed to record automatic {action} of bad actor {} in {}",
+            bad_actor.id,
+            format::display(&listener.config.guild)
+        );
+        Logger::get().error(&cache_http, e, log_msg).await;
+    }
+}
+
+fn with_moderation_context(
+    result: anyhow::Result<()>,
+    target_user: &User,
+    guild: &PartialGuild,
+) -> anyhow::Result<()> {
+    result.with_context(|| {
+        format!(
             "Error moderating {} in {}",
             format::display(target_user),
             format::display(guild)
-        );
-
-        Logger::get().error(cache_http, e, log_msg).await;
-    }
+        )
+    })
 }
 
 async fn inform_about_non_ignored(
@@ -229,15 +339,35 @@ pub fn get_moderation_action(
     }
 }
 
+/// The moderation action already happened at this point, so failing to log it must not fail the action.
+async fn send_log_message(
+    cache_http: impl CacheHttp,
+    guild: &PartialGuild,
+    log_channel: &GuildChannel,
+    content: String,
+) {
+    if let Err(e) = log_channel
+        .send_message(&cache_http, CreateMessage::new().content(content))
+        .await
+    {
+        let log_msg = format!(
+            "Failed to send the moderation log message to {}: {e}",
+            format::display(guild)
+        );
+        Logger::get().warn(&cache_http, log_msg).await;
+    }
+}
+
 async fn ban(
     cache_http: impl CacheHttp,
     guild: &PartialGuild,
     target_user: &User,
     log_channel: &GuildChannel,
     reason: impl AsRef<str>,
+    delete_message_days: u8,
 ) -> anyhow::Result<()> {
     guild
-        .ban_with_reason(cache_http.http(), target_user, 7, reason)
+        .ban_with_reason(cache_http.http(), target_user, delete_message_days, reason)
         .await?;
 
     tracing::info!(
@@ -246,12 +376,55 @@ async fn ban(
         format::display(guild)
     );
 
-    let user_msg = CreateMessage::new().content(format!(
+    let content = format!(
         "User {} was banned from your server!",
         format::fdisplay(target_user)
-    ));
+    );
 
-    log_channel.send_message(cache_http, user_msg).await?;
+    send_log_message(cache_http, guild, log_channel, content).await;
+
+    Ok(())
+}
+
+/// The Discord error code for unbanning a user that is not banned.
+const UNKNOWN_BAN: isize = 10026;
+
+fn is_unknown_ban_error(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            response.error.code == UNKNOWN_BAN
+        }
+        _ => false,
+    }
+}
+
+async fn unban(
+    cache_http: impl CacheHttp,
+    guild: &PartialGuild,
+    target_user: &User,
+    log_channel: &GuildChannel,
+) -> anyhow::Result<()> {
+    if let Err(e) = guild.unban(cache_http.http(), target_user).await {
+        // the ban was already lifted by the guild, so there is nothing left to do
+        if is_unknown_ban_error(&e) {
+            return Ok(());
+        }
+
+        return Err(e.into());
+    }
+
+    tracing::info!(
+        "Unbanned {} from {}.",
+        format::display(target_user),
+        format::display(guild)
+    );
+
+    let content = format!(
+        "User {} was unbanned from your server because their report was deactivated!",
+        format::fdisplay(target_user)
+    );
+
+    send_log_message(cache_http, guild, log_channel, content).await;
 
     Ok(())
 }
@@ -261,8 +434,11 @@ async fn soft_ban(
     guild: &PartialGuild,
     target_user: &User,
     log_channel: &GuildChannel,
+    delete_message_days: u8,
 ) -> anyhow::Result<()> {
-    guild.ban(cache_http.http(), target_user, 7).await?;
+    guild
+        .ban(cache_http.http(), target_user, delete_message_days)
+        .await?;
     guild.unban(cache_http.http(), target_user).await?;
 
     tracing::info!(
@@ -271,12 +447,12 @@ async fn soft_ban(
         format::display(guild)
     );
 
-    let user_msg = CreateMessage::new().content(format!(
+    let content = format!(
         "User {} was softbanned from your server!",
         format::fdisplay(target_user)
-    ));
+    );
 
-    log_channel.send_message(cache_http, user_msg).await?;
+    send_log_message(cache_http, guild, log_channel, content).await;
 
     Ok(())
 }
@@ -286,10 +462,11 @@ async fn timeout(
     guild: &PartialGuild,
     member: &mut Member,
     log_channel: &GuildChannel,
+    duration: Duration,
 ) -> anyhow::Result<()> {
-    let in_seven_days = Utc::now() + Days::new(7);
+    let timeout_end = Utc::now() + duration;
     member
-        .disable_communication_until_datetime(&cache_http, in_seven_days.into())
+        .disable_communication_until_datetime(&cache_http, timeout_end.into())
         .await?;
 
     tracing::info!(
@@ -298,12 +475,14 @@ async fn timeout(
         format::display(guild)
     );
 
-    let user_msg = CreateMessage::new().content(format!(
-        "User {} was timed out for 7 days!",
-        format::fdisplay(&member.user)
-    ));
+    let content = format!(
+        "User {} was timed out for `{}` minutes!\nTimeout end: {}",
+        format::fdisplay(&member.user),
+        duration.num_minutes(),
+        format::display_time(timeout_end)
+    );
 
-    log_channel.send_message(cache_http, user_msg).await?;
+    send_log_message(cache_http, guild, log_channel, content).await;
 
     Ok(())
 }
@@ -322,12 +501,12 @@ async fn kick(
         format::display(guild)
     );
 
-    let user_msg = CreateMessage::new().content(format!(
+    let content = format!(
         "User {} was kicked from your server!",
         format::fdisplay(&member.user)
-    ));
+    );
 
-    log_channel.send_message(cache_http, user_msg).await?;
+    send_log_message(cache_http, guild, log_channel, content).await;
 
     Ok(())
 }
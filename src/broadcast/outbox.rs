@@ -0,0 +1,620 @@
+use anyhow::Context;
+use futures::future;
+use poise::serenity_prelude as serenity;
+use serenity::{CacheHttp, GuildId, User, UserId};
+use sqlx::PgPool;
+
+use crate::database::controllers::badactor_model_controller::{
+    BadActor, BadActorModelController, BroadcastEmbed, BroadcastEmbedOptions,
+};
+use crate::database::controllers::outbox_model_controller::{
+    CreateOutboxDelivery, DeliveryType, OutboxDelivery, OutboxModelController,
+};
+use crate::database::controllers::webhook_model_controller::{
+    BroadcastWebhook, WebhookModelController,
+};
+use crate::util::logger::Logger;
+
+use super::broadcast_handler::{self, BroadcastType};
+use super::listener::{self, BroadcastListener};
+use super::moderate::{self, ModerateOptions};
+use super::send::{self, SendBroadcastMessageOptions};
+use super::webhooks::{self, BroadcastWebhookOptions, DiscordWebhookSink, WebhookSink};
+
+/// How long a delivery is reserved for whoever is attempting it before the worker may pick it up again.
+const LEASE_SECS: f64 = 300.0;
+/// After this many failed attempts a delivery is marked as failed and has to be replayed manually.
+const MAX_ATTEMPTS: i32 = 6;
+const BASE_RETRY_SECS: f64 = 30.0;
+const WORKER_BATCH_SIZE: i64 = 25;
+
+pub struct EnqueueOptions<'a> {
+    pub db_pool: &'a PgPool,
+    pub listeners: &'a [BroadcastListener],
+    pub webhooks: &'a [BroadcastWebhook],
+    pub bad_actor: &'a BadActor,
+    pub broadcast_type: BroadcastType,
+    pub reporting_user_id: UserId,
+    pub origin_guild_id: GuildId,
+}
+
+/// Everything needed to attempt a delivery for a single broadcast.
+pub struct DeliveryContent<'a> {
+    pub broadcast_type: BroadcastType,
+    pub bad_actor: &'a BadActor,
+    pub target_user: &'a User,
+    pub embed: &'a BroadcastEmbed,
+}
+
+/// Persist one delivery per listener log channel, per automatic moderation action and per webhook.
+/// The returned deliveries are leased, so they can be attempted right away without the worker interfering.
+pub async fn enqueue(options: EnqueueOptions<'_>) -> anyhow::Result<Vec<OutboxDelivery>> {
+    let EnqueueOptions {
+        db_pool,
+        listeners,
+        webhooks,
+        bad_actor,
+        broadcast_type,
+        reporting_user_id,
+        origin_guild_id,
+    } = options;
+
+    let new_delivery = |delivery_type, target_guild_id| CreateOutboxDelivery {
+        delivery_type,
+        target_guild_id,
+        bad_actor_id: bad_actor.id,
+        broadcast_type,
+        reporting_user_id,
+        origin_guild_id,
+    };
+
+    let mut deliveries = Vec::with_capacity(listeners.len() * 2 + webhooks.len());
+
+    for listener in listeners {
+        let guild_id = listener.config.guild.id;
+        let log_filter = &listener.config.server_config.log_filter;
+
+        if log_filter.matches(bad_actor.actor_type, broadcast_type) {
+            deliveries.push(new_delivery(DeliveryType::Listener, guild_id));
+        }
+
+        if moderate::takes_action(
+            broadcast_type,
+            bad_actor.actor_type,
+            &listener.config.server_config,
+        ) {
+            deliveries.push(new_delivery(DeliveryType::Moderation, guild_id));
+        }
+    }
+
+    for webhook in webhooks {
+        if webhook.filter.matches(bad_actor.actor_type, broadcast_type) {
+            deliveries.push(new_delivery(DeliveryType::Webhook, webhook.guild_id));
+        }
+    }
+
+    OutboxModelController::create_many(db_pool, deliveries, LEASE_SECS).await
+}
+
+/// Attempt the given deliveries and record the outcome of each one in the outbox.
+/// Listener deliveries and webhook deliveries run as separate stages, so every webhook is sent to exactly once.
+pub async fn deliver_all(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+    deliveries: &[OutboxDelivery],
+    listeners: &[BroadcastListener],
+    webhooks: &[BroadcastWebhook],
+    content: &DeliveryContent<'_>,
+) {
+    let sink = DiscordWebhookSink {
+        http: cache_http.http(),
+    };
+
+    let deliverer = DiscordDeliverer {
+        cache_http: &cache_http,
+        db_pool,
+        listeners,
+        webhooks,
+        content,
+        sink: &sink,
+    };
+
+    let webhook_options = BroadcastWebhookOptions {
+        broadcast_type: content.broadcast_type,
+        embed: content.embed,
+    };
+
+    fan_out(&deliverer, deliveries, &webhook_options).await;
+}
+
+/// The Discord and database access [fan_out] needs, so the fan-out can be tested without either.
+#[async_trait::async_trait]
+trait Deliverer: Sync {
+    type Sink: WebhookSink;
+
+    fn sink(&self) -> &Self::Sink;
+    /// The webhooks the broadcast goes to.
+    async fn webhooks(&self) -> Vec<BroadcastWebhook>;
+    async fn attempt_listener(&self, delivery: &OutboxDelivery) -> anyhow::Result<()>;
+    async fn settle(&self, delivery: &OutboxDelivery, result: anyhow::Result<()>);
+}
+
+struct DiscordDeliverer<'a, C> {
+    cache_http: &'a C,
+    db_pool: &'a PgPool,
+    listeners: &'a [BroadcastListener],
+    webhooks: &'a [BroadcastWebhook],
+    content: &'a DeliveryContent<'a>,
+    sink: &'a DiscordWebhookSink<'a>,
+}
+
+#[async_trait::async_trait]
+impl<'a, C: CacheHttp + Sync> Deliverer for DiscordDeliverer<'a, C> {
+    type Sink = DiscordWebhookSink<'a>;
+
+    fn sink(&self) -> &Self::Sink {
+        self.sink
+    }
+
+    async fn webhooks(&self) -> Vec<BroadcastWebhook> {
+        self.webhooks.to_vec()
+    }
+
+    async fn attempt_listener(&self, delivery: &OutboxDelivery) -> anyhow::Result<()> {
+        let listener = self
+            .listeners
+            .iter()
+            .find(|l| l.config.guild.id == delivery.target_guild_id);
+
+        attempt(
+            self.cache_http,
+            self.db_pool,
+            delivery,
+            listener,
+            self.content,
+        )
+        .await
+    }
+
+    async fn settle(&self, delivery: &OutboxDelivery, result: anyhow::Result<()>) {
+        settle(self.cache_http, self.db_pool, delivery, result).await;
+    }
+}
+
+/// Attempts every listener delivery and sends to all webhooks in a single stage, settling each delivery once.
+async fn fan_out(
+    deliverer: &impl Deliverer,
+    deliveries: &[OutboxDelivery],
+    webhook_options: &BroadcastWebhookOptions<'_>,
+) {
+    let (webhook_deliveries, listener_deliveries): (Vec<_>, Vec<_>) = deliveries
+        .iter()
+        .partition(|d| d.delivery_type == DeliveryType::Webhook);
+
+    let listener_stage = future::join_all(listener_deliveries.into_iter().map(|delivery| async {
+        let result = deliverer.attempt_listener(delivery).await;
+        deliverer.settle(delivery, result).await;
+    }));
+
+    tokio::join!(
+        listener_stage,
+        deliver_to_webhooks(deliverer, &webhook_deliveries, webhook_options)
+    );
+}
+
+async fn deliver_to_webhooks(
+    deliverer: &impl Deliverer,
+    deliveries: &[&OutboxDelivery],
+    webhook_options: &BroadcastWebhookOptions<'_>,
+) {
+    if deliveries.is_empty() {
+        return;
+    }
+
+    let webhooks = deliverer.webhooks().await;
+
+    let mut found_deliveries = Vec::with_capacity(deliveries.len());
+    let mut found_webhooks = Vec::with_capacity(deliveries.len());
+
+    for delivery in deliveries {
+        let webhook = webhooks
+            .iter()
+            .find(|w| w.guild_id == delivery.target_guild_id);
+
+        match webhook {
+            Some(webhook) => {
+                found_deliveries.push(*delivery);
+                found_webhooks.push(webhook.clone());
+            }
+            None => {
+                let e = anyhow::anyhow!(
+                    "There is no webhook for guild {} in the database",
+                    delivery.target_guild_id
+                );
+                deliverer.settle(delivery, Err(e)).await;
+            }
+        }
+    }
+
+    let results =
+        webhooks::broadcast_to_webhooks(deliverer.sink(), &found_webhooks, webhook_options).await;
+
+    let futures = found_deliveries
+        .into_iter()
+        .zip(results)
+        .map(|(delivery, result)| deliverer.settle(delivery, result));
+
+    future::join_all(futures).await;
+}
+
+/// Attempts all pending deliveries that are due.
+pub async fn process_due(ctx: &serenity::Context, db_pool: &PgPool) -> anyhow::Result<()> {
+    let deliveries =
+        OutboxModelController::claim_due(db_pool, WORKER_BATCH_SIZE, LEASE_SECS).await?;
+
+    for delivery in deliveries {
+        let Some(bad_actor) =
+            BadActorModelController::get_by_id(db_pool, delivery.bad_actor_id).await?
+        else {
+            // the report was deleted in the meantime, so there is nothing left to deliver
+            OutboxModelController::delete(db_pool, delivery.id).await?;
+            continue;
+        };
+
+        // e.g. a ban retried after the report was deactivated, or an unban replayed after it was reactivated
+        if delivery.broadcast_type.is_obsolete_for(bad_actor.is_active) {
+            tracing::info!(
+                "Dropping obsolete {} delivery {} of report {}, the report is {}",
+                delivery.delivery_type,
+                delivery.id,
+                delivery.bad_actor_id,
+                if bad_actor.is_active {
+                    "active"
+                } else {
+                    "inactive"
+                }
+            );
+            OutboxModelController::delete(db_pool, delivery.id).await?;
+            continue;
+        }
+
+        let result = attempt_from_db(ctx, db_pool, &delivery, &bad_actor).await;
+        settle(ctx, db_pool, &delivery, result).await;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the broadcast content from the current state of the bad actor and attempts the delivery.
+async fn attempt_from_db(
+    ctx: &serenity::Context,
+    db_pool: &PgPool,
+    delivery: &OutboxDelivery,
+    bad_actor: &BadActor,
+) -> anyhow::Result<()> {
+    let target_user = bad_actor
+        .user_id
+        .to_user(ctx)
+        .await
+        .context("Failed to get the user of the bad actor")?;
+
+    let reporting_user = delivery
+        .reporting_user_id
+        .to_user(ctx)
+        .await
+        .context("Failed to get the reporting user")?;
+
+    let embed_options = BroadcastEmbedOptions {
+        origin_guild_id: delivery.origin_guild_id,
+        origin_guild: delivery.origin_guild_id.to_partial_guild(ctx).await.ok(),
+        report_author: &reporting_user,
+        bot_id: ctx.cache.current_user().id,
+        db_pool,
+    };
+
+    let embed_colour = broadcast_handler::get_embed_colour(delivery.broadcast_type);
+
+    let embed = bad_actor
+        .to_broadcast_embed(ctx, embed_options, embed_colour)
+        .await;
+
+    let content = DeliveryContent {
+        broadcast_type: delivery.broadcast_type,
+        bad_actor,
+        target_user: &target_user,
+        embed: &embed,
+    };
+
+    attempt(ctx, db_pool, delivery, None, &content).await
+}
+
+async fn attempt(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+    delivery: &OutboxDelivery,
+    listener: Option<&BroadcastListener>,
+    content: &DeliveryContent<'_>,
+) -> anyhow::Result<()> {
+    if delivery.delivery_type == DeliveryType::Webhook {
+        let Some(webhook) =
+            WebhookModelController::get_by_guild_id(db_pool, delivery.target_guild_id).await?
+        else {
+            anyhow::bail!(
+                "There is no webhook for guild {} in the database",
+                delivery.target_guild_id
+            );
+        };
+
+        let sink = DiscordWebhookSink {
+            http: cache_http.http(),
+        };
+
+        let webhook_options = BroadcastWebhookOptions {
+            broadcast_type: content.broadcast_type,
+            embed: content.embed,
+        };
+
+        return webhooks::send_to_webhook(&sink, &webhook, &webhook_options).await;
+    }
+
+    let fetched_listener;
+    let listener = match listener {
+        Some(listener) => listener,
+        None => {
+            fetched_listener =
+                listener::get_valid_listener(&cache_http, db_pool, delivery.target_guild_id)
+                    .await?;
+            &fetched_listener
+        }
+    };
+
+    if delivery.delivery_type == DeliveryType::Moderation {
+        let moderate_options = ModerateOptions {
+            db_pool,
+            broadcast_type: content.broadcast_type,
+            listener,
+            bad_actor: content.bad_actor,
+            target_user: content.target_user,
+        };
+
+        return moderate::moderate(&cache_http, moderate_options).await;
+    }
+
+    let send_options = SendBroadcastMessageOptions {
+        broadcast_type: content.broadcast_type,
+        listener,
+        bad_actor: content.bad_actor,
+        embed: content.embed,
+    };
+
+    send::send_broadcast_message(&cache_http, send_options).await
+}
+
+/// Removes successful deliveries from the outbox and schedules retries for failed ones.
+async fn settle(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+    delivery: &OutboxDelivery,
+    result: anyhow::Result<()>,
+) {
+    let update_result = match result {
+        Ok(()) => OutboxModelController::delete(db_pool, delivery.id).await,
+        Err(e) => {
+            let attempts = delivery.attempts + 1;
+            let retry_in_secs = (attempts < MAX_ATTEMPTS).then(|| retry_delay_secs(attempts));
+
+            let description = format!(
+                "{} delivery {} of report {} to guild {}",
+                delivery.delivery_type,
+                delivery.id,
+                delivery.bad_actor_id,
+                delivery.target_guild_id
+            );
+
+            match retry_in_secs {
+                Some(secs) => {
+                    let log_msg = format!(
+                        "Attempt {attempts} of {description} failed, retrying in {secs}s: {e:#}"
+                    );
+                    Logger::get().warn(&cache_http, log_msg).await;
+                }
+                None => {
+                    let log_msg = format!("Giving up on {description} after {attempts} attempts");
+                    Logger::get()
+                        .error(&cache_http, format!("{e:#}"), log_msg)
+                        .await;
+                }
+            }
+
+            OutboxModelController::record_failure(
+                db_pool,
+                delivery.id,
+                format!("{e:#}"),
+                retry_in_secs,
+            )
+            .await
+        }
+    };
+
+    if let Err(e) = update_result {
+        let log_msg = format!("Failed to update delivery {} in the outbox", delivery.id);
+        Logger::get().error(&cache_http, e, log_msg).await;
+    }
+}
+
+/// Exponential backoff: 30s, 60s, 2min, 4min, 8min.
+fn retry_delay_secs(attempts: i32) -> f64 {
+    BASE_RETRY_SECS * 2f64.powi(attempts - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use chrono::Utc;
+    use poise::serenity_prelude::{CreateEmbed, ExecuteWebhook};
+    use url::Url;
+
+    use super::*;
+    use crate::broadcast::filter::BroadcastFilter;
+
+    #[derive(Default)]
+    struct MockSink {
+        received: Mutex<Vec<GuildId>>,
+    }
+
+    #[async_trait::async_trait]
+    impl WebhookSink for MockSink {
+        async fn execute(
+            &self,
+            webhook: &BroadcastWebhook,
+            _execute: ExecuteWebhook,
+        ) -> anyhow::Result<()> {
+            self.received.lock().unwrap().push(webhook.guild_id);
+            Ok(())
+        }
+    }
+
+    /// Counts what [fan_out] does instead of talking to Discord and the database.
+    #[derive(Default)]
+    struct MockDeliverer {
+        sink: MockSink,
+        webhooks: Vec<BroadcastWebhook>,
+        webhook_lookups: AtomicUsize,
+        attempted: Mutex<Vec<i32>>,
+        settled: Mutex<Vec<(i32, bool)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Deliverer for MockDeliverer {
+        type Sink = MockSink;
+
+        fn sink(&self) -> &Self::Sink {
+            &self.sink
+        }
+
+        async fn webhooks(&self) -> Vec<BroadcastWebhook> {
+            self.webhook_lookups.fetch_add(1, Ordering::SeqCst);
+            self.webhooks.clone()
+        }
+
+        async fn attempt_listener(&self, delivery: &OutboxDelivery) -> anyhow::Result<()> {
+            self.attempted.lock().unwrap().push(delivery.id);
+            Ok(())
+        }
+
+        async fn settle(&self, delivery: &OutboxDelivery, result: anyhow::Result<()>) {
+            self.settled
+                .lock()
+                .unwrap()
+                .push((delivery.id, result.is_ok()));
+        }
+    }
+
+    fn webhook(guild_id: u64) -> BroadcastWebhook {
+        BroadcastWebhook {
+            guild_id: GuildId::new(guild_id),
+            guild_name: format!("Guild {guild_id}"),
+            webhook_url: Url::parse(&format!(
+                "https://discord.com/api/webhooks/{guild_id}/token"
+            ))
+            .unwrap(),
+            filter: BroadcastFilter::default(),
+        }
+    }
+
+    fn delivery(id: i32, delivery_type: DeliveryType, target_guild_id: u64) -> OutboxDelivery {
+        OutboxDelivery {
+            id,
+            delivery_type,
+            target_guild_id: GuildId::new(target_guild_id),
+            bad_actor_id: 1,
+            broadcast_type: BroadcastType::Report,
+            reporting_user_id: UserId::new(1),
+            origin_guild_id: GuildId::new(1),
+            attempts: 0,
+            last_error: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn sends_to_each_webhook_once_across_listeners() {
+        let deliverer = MockDeliverer {
+            webhooks: vec![webhook(11), webhook(12), webhook(13)],
+            ..Default::default()
+        };
+
+        let mut deliveries = Vec::new();
+        for guild_id in 1..=3 {
+            let id = deliveries.len() as i32;
+            deliveries.push(delivery(id, DeliveryType::Listener, guild_id));
+            deliveries.push(delivery(id + 1, DeliveryType::Moderation, guild_id));
+        }
+        for guild_id in 11..=13 {
+            let id = deliveries.len() as i32;
+            deliveries.push(delivery(id, DeliveryType::Webhook, guild_id));
+        }
+
+        let embed = BroadcastEmbed {
+            embed: CreateEmbed::default(),
+            gallery: Vec::new(),
+            attachments: Vec::new(),
+        };
+        let webhook_options = BroadcastWebhookOptions {
+            broadcast_type: BroadcastType::Report,
+            embed: &embed,
+        };
+
+        futures::executor::block_on(fan_out(&deliverer, &deliveries, &webhook_options));
+
+        let mut received = deliverer.sink.received.into_inner().unwrap();
+        received.sort();
+        assert_eq!(
+            received,
+            vec![GuildId::new(11), GuildId::new(12), GuildId::new(13)]
+        );
+        assert_eq!(deliverer.webhook_lookups.load(Ordering::SeqCst), 1);
+
+        let mut attempted = deliverer.attempted.into_inner().unwrap();
+        attempted.sort();
+        assert_eq!(attempted, (0..6).collect::<Vec<_>>());
+
+        let mut settled = deliverer.settled.into_inner().unwrap();
+        settled.sort();
+        assert_eq!(settled, (0..9).map(|id| (id, true)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn settles_webhook_deliveries_without_a_webhook_as_failed() {
+        let deliverer = MockDeliverer {
+            webhooks: vec![webhook(11)],
+            ..Default::default()
+        };
+        let deliveries = vec![
+            delivery(0, DeliveryType::Webhook, 11),
+            delivery(1, DeliveryType::Webhook, 12),
+        ];
+
+        let embed = BroadcastEmbed {
+            embed: CreateEmbed::default(),
+            gallery: Vec::new(),
+            attachments: Vec::new(),
+        };
+        let webhook_options = BroadcastWebhookOptions {
+            broadcast_type: BroadcastType::Report,
+            embed: &embed,
+        };
+
+        futures::executor::block_on(fan_out(&deliverer, &deliveries, &webhook_options));
+
+        let mut settled = deliverer.settled.into_inner().unwrap();
+        settled.sort();
+        assert_eq!(settled, vec![(0, true), (1, false)]);
+        assert_eq!(
+            deliverer.sink.received.into_inner().unwrap(),
+            vec![GuildId::new(11)]
+        );
+    }
+}
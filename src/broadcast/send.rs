@@ -1,12 +1,12 @@
+use anyhow::Context;
 use poise::serenity_prelude as serenity;
-use serenity::{CacheHttp, CreateAttachment, CreateEmbed, Mentionable};
+use serenity::{CacheHttp, Mentionable};
 
-use crate::database::controllers::badactor_model_controller::BadActor;
+use crate::database::controllers::badactor_model_controller::{BadActor, BroadcastEmbed};
 use crate::database::controllers::serverconfig_model_controller::{
     ActionLevel, ServerConfigComplete,
 };
 use crate::format;
-use crate::util::logger::Logger;
 
 use super::broadcast_handler::{self, get_broadcast_message};
 use super::listener::BroadcastListener;
@@ -16,20 +16,18 @@ pub struct SendBroadcastMessageOptions<'a> {
     pub broadcast_type: broadcast_handler::BroadcastType,
     pub listener: &'a BroadcastListener,
     pub bad_actor: &'a BadActor,
-    pub embed: &'a CreateEmbed,
-    pub attachment: &'a Option<CreateAttachment>,
+    pub embed: &'a BroadcastEmbed,
 }
 
 pub async fn send_broadcast_message(
     cache_http: impl CacheHttp,
     options: SendBroadcastMessageOptions<'_>,
-) {
+) -> anyhow::Result<()> {
     let SendBroadcastMessageOptions {
         broadcast_type,
         listener,
         bad_actor,
         embed,
-        attachment,
     } = options;
     let action_level = get_moderation_action(
         broadcast_type,
@@ -45,24 +43,25 @@ pub async fn send_broadcast_message(
     );
     let message = get_broadcast_message(
         &content,
+        bad_actor,
         embed.clone(),
-        attachment.clone(),
         action_level,
         broadcast_type,
     );
 
-    if let Err(e) = listener
+    listener
         .log_channel
         .send_message(&cache_http, message)
         .await
-    {
-        let log_msg = format!(
-            "Failed to send broadcast embed to #{} in {}",
-            listener.log_channel.name,
-            format::display(&listener.config.guild)
-        );
-        Logger::get().error(&cache_http, e, log_msg).await;
-    }
+        .with_context(|| {
+            format!(
+                "Failed to send broadcast embed to #{} in {}",
+                listener.log_channel.name,
+                format::display(&listener.config.guild)
+            )
+        })?;
+
+    Ok(())
 }
 
 fn get_message_with_pings(
@@ -1,182 +1,272 @@
-use std::str::FromStr;
+use std::sync::OnceLock;
 
+use anyhow::Context;
+use dashmap::DashMap;
 use futures::future;
 use poise::serenity_prelude as serenity;
-use serenity::{CacheHttp, CreateAttachment, CreateEmbed, ExecuteWebhook, GuildId, Webhook};
-use sqlx::prelude::FromRow;
+use serenity::{CacheHttp, ExecuteWebhook, GuildId, Http, HttpError, Webhook};
 use sqlx::PgPool;
 use url::Url;
 
-use crate::util::format;
+use crate::database::controllers::badactor_model_controller::BroadcastEmbed;
+use crate::database::controllers::webhook_model_controller::{
+    BroadcastWebhook, WebhookModelController,
+};
 use crate::util::logger::Logger;
 
 use super::broadcast_handler::BroadcastType;
 
-#[derive(Debug, FromRow)]
-struct DbBroadcastWebhook {
-    guild_id: String,
-    guild_name: String,
-    webhook_url: String,
+/// Somewhere a broadcast webhook message can be sent to.
+#[async_trait::async_trait]
+pub trait WebhookSink: Sync {
+    async fn execute(
+        &self,
+        webhook: &BroadcastWebhook,
+        execute: ExecuteWebhook,
+    ) -> anyhow::Result<()>;
+}
+
+/// Sends webhook messages to Discord, reusing resolved [Webhook]s between broadcasts.
+pub struct DiscordWebhookSink<'a> {
+    pub http: &'a Http,
+}
+
+#[async_trait::async_trait]
+impl WebhookSink for DiscordWebhookSink<'_> {
+    async fn execute(
+        &self,
+        webhook: &BroadcastWebhook,
+        execute: ExecuteWebhook,
+    ) -> anyhow::Result<()> {
+        let discord_webhook = resolve_webhook(self.http, webhook).await?;
+
+        if let Err(e) = discord_webhook.execute(self.http, false, execute).await {
+            // the webhook might have been deleted or edited, so resolve it again on the next attempt
+            webhook_cache().remove(&webhook.guild_id);
+
+            return Err(e).with_context(|| {
+                format!(
+                    "Failed to send broadcast embed to webhook in guild {} ({})",
+                    webhook.guild_name, webhook.guild_id
+                )
+            });
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
-struct BroadcastWebhook {
-    guild_id: GuildId,
-    guild_name: String,
+struct CachedWebhook {
     webhook_url: Url,
+    webhook: Webhook,
 }
 
-impl TryFrom<DbBroadcastWebhook> for BroadcastWebhook {
-    type Error = anyhow::Error;
+static WEBHOOK_CACHE: OnceLock<DashMap<GuildId, CachedWebhook>> = OnceLock::new();
 
-    fn try_from(db_webhook: DbBroadcastWebhook) -> Result<Self, Self::Error> {
-        let guild_id = GuildId::from_str(&db_webhook.guild_id)?;
-        let webhook_url = Url::from_str(&db_webhook.webhook_url)?;
+fn webhook_cache() -> &'static DashMap<GuildId, CachedWebhook> {
+    WEBHOOK_CACHE.get_or_init(DashMap::new)
+}
 
-        Ok(Self {
-            guild_id,
-            webhook_url,
-            guild_name: db_webhook.guild_name,
-        })
+/// Gets the [Webhook] from the cache or from Discord if it is not cached or its URL changed.
+async fn resolve_webhook(http: &Http, webhook: &BroadcastWebhook) -> anyhow::Result<Webhook> {
+    if let Some(cached) = webhook_cache().get(&webhook.guild_id) {
+        if cached.webhook_url == webhook.webhook_url {
+            return Ok(cached.webhook.clone());
+        }
     }
+
+    let discord_webhook = Webhook::from_url(http, webhook.webhook_url.as_str())
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to connect to webhook in guild {} ({})",
+                webhook.guild_name, webhook.guild_id
+            )
+        })?;
+
+    let cached = CachedWebhook {
+        webhook_url: webhook.webhook_url.clone(),
+        webhook: discord_webhook.clone(),
+    };
+
+    webhook_cache().insert(webhook.guild_id, cached);
+
+    Ok(discord_webhook)
 }
 
-#[derive(Debug)]
-struct WebhookListenerResult {
-    guild_id: GuildId,
-    guild_name: String,
-    webhook: anyhow::Result<Webhook>,
+/// The Discord error codes for a webhook that was deleted or whose token was regenerated.
+const UNKNOWN_WEBHOOK: isize = 10015;
+const INVALID_WEBHOOK_TOKEN: isize = 50027;
+
+fn is_stale_webhook_error(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            matches!(response.error.code, UNKNOWN_WEBHOOK | INVALID_WEBHOOK_TOKEN)
+        }
+        _ => false,
+    }
 }
 
-#[derive(Debug)]
-struct WebhookListener {
-    guild_id: GuildId,
-    guild_name: String,
-    webhook: Webhook,
+/// Removes webhooks that no longer exist on Discord and forgets cached webhooks that are no longer configured.
+/// Webhooks that can't be reached for any other reason are kept, the error might be temporary.
+pub async fn remove_stale_webhooks(
+    cache_http: impl CacheHttp,
+    db_pool: &PgPool,
+) -> anyhow::Result<()> {
+    let webhooks = WebhookModelController::get_all(db_pool).await?;
+
+    webhook_cache().retain(|guild_id, cached| {
+        webhooks
+            .iter()
+            .any(|w| w.guild_id == *guild_id && w.webhook_url == cached.webhook_url)
+    });
+
+    for webhook in webhooks {
+        let Err(e) = Webhook::from_url(cache_http.http(), webhook.webhook_url.as_str()).await
+        else {
+            continue;
+        };
+
+        if !is_stale_webhook_error(&e) {
+            tracing::warn!(
+                "Failed to check the webhook in guild {} ({}): {e}",
+                webhook.guild_name,
+                webhook.guild_id
+            );
+            continue;
+        }
+
+        WebhookModelController::delete(db_pool, webhook.guild_id).await?;
+        webhook_cache().remove(&webhook.guild_id);
+
+        let log_msg = format!(
+            "Removed the webhook for {} (`{}`) because it no longer exists on Discord.",
+            webhook.guild_name, webhook.guild_id
+        );
+        Logger::get().warn(&cache_http, log_msg).await;
+    }
+
+    Ok(())
 }
 
 pub struct BroadcastWebhookOptions<'a> {
-    pub db_pool: &'a PgPool,
     pub broadcast_type: BroadcastType,
-    pub embed: &'a CreateEmbed,
-    pub attachment: &'a Option<CreateAttachment>,
+    pub embed: &'a BroadcastEmbed,
 }
 
+/// Sends the broadcast embed to every webhook once.
+/// The results are in the same order as the webhooks.
 pub async fn broadcast_to_webhooks(
-    cache_http: impl CacheHttp,
-    options: BroadcastWebhookOptions<'_>,
-) {
+    sink: &impl WebhookSink,
+    webhooks: &[BroadcastWebhook],
+    options: &BroadcastWebhookOptions<'_>,
+) -> Vec<anyhow::Result<()>> {
+    let futures = webhooks
+        .iter()
+        .map(|webhook| send_to_webhook(sink, webhook, options));
+
+    future::join_all(futures).await
+}
+
+/// Sends the broadcast embed to a single webhook.
+pub async fn send_to_webhook(
+    sink: &impl WebhookSink,
+    webhook: &BroadcastWebhook,
+    options: &BroadcastWebhookOptions<'_>,
+) -> anyhow::Result<()> {
     let BroadcastWebhookOptions {
-        db_pool,
         broadcast_type,
         embed,
-        attachment,
-    } = options;
+    } = *options;
 
-    let webhooks = match get_webhooks_from_db(db_pool).await {
-        Ok(webhooks) => webhooks,
-        Err(e) => {
-            let log_msg = "Failed to get webhooks to broadcast to from the database";
-            Logger::get().error(&cache_http, e, log_msg).await;
+    let execute = ExecuteWebhook::default()
+        .content(broadcast_type.message())
+        .embeds(embed.embeds())
+        .add_files(embed.attachments.clone());
 
-            return;
-        }
-    };
+    sink.execute(webhook, execute).await
+}
 
-    let webhooks = get_discord_webhooks(&cache_http, webhooks).await;
-
-    let futures = webhooks.into_iter().map(|l| {
-        let execute = if let Some(attachment) = attachment.clone() {
-            ExecuteWebhook::default()
-                .content(broadcast_type.message())
-                .embed(embed.clone())
-                .add_file(attachment)
-        } else {
-            ExecuteWebhook::default()
-                .content(broadcast_type.message())
-                .embed(embed.clone())
-        };
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
 
-        let http = cache_http.http();
+    use poise::serenity_prelude::CreateEmbed;
 
-        async move {
-            if let Err(e) = l.webhook.execute(http, false, execute).await {
-                let log_msg = format!(
-                    "Failed to send broadcast embed to webhook in guild {} ({})",
-                    l.guild_name, l.guild_id
-                );
-                Logger::get().error(http, e, log_msg).await;
-            }
-        }
-    });
+    use super::*;
+    use crate::broadcast::filter::BroadcastFilter;
 
-    future::join_all(futures).await;
-}
+    /// Records which guilds a webhook message was sent to instead of sending it.
+    #[derive(Default)]
+    struct MockSink {
+        received: Mutex<Vec<GuildId>>,
+        failing: Vec<GuildId>,
+    }
 
-async fn get_webhooks_from_db(db_pool: &PgPool) -> anyhow::Result<Vec<BroadcastWebhook>> {
-    sqlx::query_as::<_, DbBroadcastWebhook>("SELECT * FROM webhooks;")
-        .fetch_all(db_pool)
-        .await?
-        .into_iter()
-        .map(BroadcastWebhook::try_from)
-        .collect::<anyhow::Result<Vec<_>>>()
-}
+    #[async_trait::async_trait]
+    impl WebhookSink for MockSink {
+        async fn execute(
+            &self,
+            webhook: &BroadcastWebhook,
+            _execute: ExecuteWebhook,
+        ) -> anyhow::Result<()> {
+            self.received.lock().unwrap().push(webhook.guild_id);
 
-async fn get_discord_webhooks(
-    cache_http: impl CacheHttp,
-    webhooks: Vec<BroadcastWebhook>,
-) -> Vec<WebhookListener> {
-    let len = webhooks.len();
-    let http = cache_http.http();
-
-    let iter = webhooks.into_iter().map(|w| async move {
-        let webhook = Webhook::from_url(http, w.webhook_url.as_str())
-            .await
-            .map_err(anyhow::Error::from);
-
-        WebhookListenerResult {
-            guild_id: w.guild_id,
-            guild_name: w.guild_name,
-            webhook,
+            if self.failing.contains(&webhook.guild_id) {
+                anyhow::bail!("Webhook in guild {} is unavailable", webhook.guild_id);
+            }
+
+            Ok(())
         }
-    });
+    }
 
-    let results = future::join_all(iter).await;
+    fn webhook(id: u64) -> BroadcastWebhook {
+        BroadcastWebhook {
+            guild_id: GuildId::new(id),
+            guild_name: format!("Guild {id}"),
+            webhook_url: Url::parse(&format!("https://discord.com/api/webhooks/{id}/token"))
+                .unwrap(),
+            filter: BroadcastFilter::default(),
+        }
+    }
 
-    let mut good_webhooks = Vec::with_capacity(len);
+    fn broadcast_embed() -> BroadcastEmbed {
+        BroadcastEmbed {
+            embed: CreateEmbed::default(),
+            gallery: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
 
-    for listener in results {
-        match listener.webhook {
-            Ok(webhook) => {
-                let listener = WebhookListener {
-                    guild_id: listener.guild_id,
-                    guild_name: listener.guild_name,
-                    webhook,
-                };
+    fn received_counts(sink: &MockSink, webhooks: &[BroadcastWebhook]) -> Vec<usize> {
+        let received = sink.received.lock().unwrap();
 
-                good_webhooks.push(listener);
-            }
-            Err(e) => {
-                let logger = Logger::get();
-
-                if let Ok(guild) = listener.guild_id.to_partial_guild(&cache_http).await {
-                    let log_msg = format!(
-                        "Failed to connect to webhook in guild {}",
-                        format::display(&guild)
-                    );
-
-                    logger.error(&cache_http, e, log_msg).await;
-                } else {
-                    let log_msg = format!(
-                        "Failed to connect to webhook in guild {} ({})",
-                        listener.guild_name, listener.guild_id
-                    );
-
-                    logger.error(&cache_http, e, log_msg).await;
-                }
-            }
-        }
+        webhooks
+            .iter()
+            .map(|w| received.iter().filter(|id| **id == w.guild_id).count())
+            .collect()
     }
 
-    good_webhooks
+    #[test]
+    fn failing_webhook_does_not_affect_others() {
+        let sink = MockSink {
+            failing: vec![GuildId::new(2)],
+            ..Default::default()
+        };
+        let webhooks = vec![webhook(1), webhook(2), webhook(3)];
+        let embed = broadcast_embed();
+
+        let options = BroadcastWebhookOptions {
+            broadcast_type: BroadcastType::Deactivate,
+            embed: &embed,
+        };
+
+        let results =
+            futures::executor::block_on(broadcast_to_webhooks(&sink, &webhooks, &options));
+
+        let failed = results.iter().map(|r| r.is_err()).collect::<Vec<_>>();
+        assert_eq!(failed, vec![false, true, false]);
+        assert_eq!(received_counts(&sink, &webhooks), vec![1, 1, 1]);
+    }
 }
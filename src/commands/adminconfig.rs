@@ -1,17 +1,34 @@
+use std::str::FromStr;
+
+use chrono::Utc;
 use poise::CreateReply;
 use serenity::all::CacheHttp;
 
-use crate::database::controllers::badactor_model_controller::BadActorModelController;
+use crate::commands::webhook::valid_actor_types;
+use crate::database::controllers::badactor_model_controller::{
+    BadActorModelController, BadActorType,
+};
+use crate::database::controllers::dmtemplate_model_controller::DmTemplateModelController;
+use crate::database::controllers::evidence_model_controller::EvidenceModelController;
+use crate::database::controllers::outbox_model_controller::OutboxModelController;
 use crate::database::controllers::serverconfig_model_controller::{
     ServerConfigComplete, ServerConfigModelController,
 };
 use crate::util::embeds::CreateJanitorEmbed;
-use crate::util::format::display_guild_ids;
+use crate::util::format::{self, display_guild_ids};
+use crate::util::logger::Logger;
+use crate::util::parsing::parse_duration;
 use crate::util::parsing::parse_guild_ids;
-use crate::util::screenshot::FileManager;
+use crate::util::screenshot::{FileManager, GcMode};
 use crate::AppContext;
 use crate::{assert_admin, assert_admin_server};
 
+/// The maximum length of a DM template, leaving room for the filled in placeholders.
+const MAX_DM_TEMPLATE_LEN: usize = 1800;
+
+/// How old unused files have to be before the garbage collection touches them.
+const DEFAULT_GC_GRACE_PERIOD: &str = "1d";
+
 /// Subcommands for admins to inspect the bot's server configs.
 #[poise::command(
     slash_command,
@@ -20,7 +37,14 @@ use crate::{assert_admin, assert_admin_server};
         "display_configs",
         "delete_bad_actor",
         "display_config_guilds",
-        "display_guilds"
+        "display_guilds",
+        "display_failed_deliveries",
+        "replay_deliveries",
+        "display_dm_templates",
+        "dm_template",
+        "jobs",
+        "verify_files",
+        "collect_garbage"
     ),
     subcommand_required
 )]
@@ -118,10 +142,27 @@ async fn delete_bad_actor(
     assert_admin_server!(ctx);
     ctx.defer().await?;
 
-    let deleted = BadActorModelController::delete(&ctx.data().db_pool, entry).await?;
+    // SAFETY: assert_admin_server!() returns if guild_id is None
+    let guild_id = ctx.guild_id().unwrap();
+
+    // the evidence rows are deleted along with the entry, so the files have to be looked up first
+    let evidence = EvidenceModelController::get_by_bad_actor_id(&ctx.data().db_pool, entry).await?;
 
-    if let Some(file_name) = deleted.screenshot_proof.as_ref() {
-        FileManager::delete(file_name).await?;
+    let deleted =
+        BadActorModelController::delete(&ctx.data().db_pool, entry, ctx.author().id, guild_id)
+            .await?;
+
+    let file_names = deleted
+        .screenshot_proof
+        .into_iter()
+        .chain(evidence.into_iter().map(|e| e.file_name));
+
+    // the entry is already gone, leftover files are cleaned up by the garbage collection
+    for file_name in file_names {
+        if let Err(e) = FileManager::delete(&ctx.data().db_pool, &file_name).await {
+            let log_msg = format!("Failed to delete file {file_name} of bad actor entry {entry}");
+            Logger::get().error(ctx, e, log_msg).await;
+        }
     }
 
     let reply = format!("Successfully deleted bad actor entry with id {entry} from the database.");
@@ -129,3 +170,344 @@ async fn delete_bad_actor(
     ctx.say(reply).await?;
     Ok(())
 }
+
+/// Display the most recent broadcast deliveries that ran out of attempts.
+#[poise::command(slash_command)]
+async fn display_failed_deliveries(ctx: AppContext<'_>) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let deliveries = OutboxModelController::get_failed(&ctx.data().db_pool, 10).await?;
+
+    if deliveries.is_empty() {
+        ctx.say("There are no failed deliveries.").await?;
+        return Ok(());
+    }
+
+    let mut embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("Failed Broadcast Deliveries");
+
+    for delivery in deliveries {
+        let name = format!("#{} {}", delivery.id, delivery.delivery_type);
+        let value = format!(
+            "Report: {}\nBroadcast: {}\nTarget Server: {}\nAttempts: {}\nLast Attempt: {}\nError: {}",
+            delivery.bad_actor_id,
+            delivery.broadcast_type,
+            delivery.target_guild_id,
+            delivery.attempts,
+            format::time(delivery.updated_at, format::TimestampStyle::ShortDateTime),
+            format::truncate(
+                delivery.last_error.as_deref().unwrap_or("Unknown error."),
+                300
+            )
+        );
+
+        embed = embed.field(name, value, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Queue failed broadcast deliveries to be attempted again.
+#[poise::command(slash_command)]
+async fn replay_deliveries(
+    ctx: AppContext<'_>,
+    #[description = "The ID of the delivery to replay. Replays all failed deliveries if omitted."]
+    delivery_id: Option<i32>,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let replayed = OutboxModelController::replay(&ctx.data().db_pool, delivery_id).await?;
+
+    let reply = match (delivery_id, replayed) {
+        (Some(id), 0) => format!("There is no failed delivery with ID {id}."),
+        (Some(id), _) => format!("Delivery {id} will be attempted again shortly."),
+        (None, 0) => "There are no failed deliveries to replay.".to_string(),
+        (None, count) => format!("{count} failed deliveries will be attempted again shortly."),
+    };
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Display the DMs reported users receive for each bad actor type.
+#[poise::command(slash_command)]
+async fn display_dm_templates(ctx: AppContext<'_>) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let templates = DmTemplateModelController::get_all(&ctx.data().db_pool).await?;
+
+    let mut embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("DM Templates")
+        .description("Placeholders: `{id}` (report ID), `{type}` (bad actor type), `{origin}` (server of origin).");
+
+    for template in templates {
+        let kind = if template.content.is_some() {
+            "custom"
+        } else {
+            "built-in"
+        };
+
+        let name = format!(
+            "{} ({kind}, {})",
+            template.actor_type,
+            if template.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+
+        embed = embed.field(name, format::truncate(template.template(), 1024), false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Change the DM reported users receive for a bad actor type.
+#[poise::command(slash_command)]
+async fn dm_template(
+    ctx: AppContext<'_>,
+    #[description = "The bad actor type, e.g. spam or bigotry."] actor_type: String,
+    #[description = "The new template. Supports {id}, {type} and {origin}. Use \\n for line breaks."]
+    content: Option<String>,
+    #[description = "Go back to the built-in template."] reset: Option<bool>,
+    #[description = "Whether reported users of this type receive a DM at all."] enabled: Option<
+        bool,
+    >,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let Ok(actor_type) = BadActorType::from_str(actor_type.trim()) else {
+        ctx.say(format!(
+            "Invalid bad actor type. Valid types are: {}.",
+            valid_actor_types()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    if content.is_some() && reset == Some(true) {
+        ctx.say("You can either set a new template or reset it, not both.")
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(content) = content.as_ref() {
+        if content.chars().count() > MAX_DM_TEMPLATE_LEN {
+            ctx.say(format!(
+                "The template is too long. Please keep it under {MAX_DM_TEMPLATE_LEN} characters."
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let db_pool = &ctx.data().db_pool;
+
+    if content.is_some() || reset == Some(true) {
+        let content = content.map(|c| c.replace("\\n", "\n"));
+        DmTemplateModelController::set_content(db_pool, actor_type, content).await?;
+    }
+
+    if let Some(enabled) = enabled {
+        DmTemplateModelController::set_enabled(db_pool, actor_type, enabled).await?;
+    }
+
+    let template = DmTemplateModelController::get(db_pool, actor_type).await?;
+
+    let reply = format!(
+        "The DM for {actor_type} reports is {}. It reads:\n\n{}",
+        if template.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        template.render(0, "Example Server")
+    );
+
+    ctx.say(format::truncate(&reply, 2000)).await?;
+    Ok(())
+}
+
+/// Display the status of the background jobs or run one right away.
+#[poise::command(slash_command)]
+async fn jobs(
+    ctx: AppContext<'_>,
+    #[description = "The name of a job to run right away."] run: Option<String>,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let scheduler = &ctx.data().scheduler;
+
+    if let Some(name) = run {
+        let reply = match scheduler.trigger(name.trim()) {
+            Ok(true) => format!("Started job `{}`.", name.trim()),
+            Ok(false) => format!("Job `{}` is already running.", name.trim()),
+            Err(e) => format!("{e}."),
+        };
+
+        ctx.say(reply).await?;
+        return Ok(());
+    }
+
+    let mut embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("Background Jobs");
+
+    for job in scheduler.jobs() {
+        let display_time = |time: Option<chrono::DateTime<chrono::Utc>>| {
+            time.map(|t| format::time(t, format::TimestampStyle::Relative))
+                .unwrap_or("Never".to_string())
+        };
+
+        let last_result = match (&job.status.last_error, job.status.last_finished) {
+            (_, None) => "-".to_string(),
+            (None, Some(_)) => "Success".to_string(),
+            (Some(e), Some(_)) => format!("Failed: {}", format::truncate(e, 300)),
+        };
+
+        let value = format!(
+            "Interval: {}s\nRunning: {}\nLast Started: {}\nLast Finished: {}\nLast Result: {}\nRuns: {} ({} failed)",
+            job.interval.as_secs(),
+            format::display_bool(job.running),
+            display_time(job.status.last_started),
+            display_time(job.status.last_finished),
+            last_result,
+            job.status.runs,
+            job.status.failures
+        );
+
+        embed = embed.field(format!("`{}`", job.name), value, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Check stored screenshots and evidence against their recorded hashes and list files without one.
+#[poise::command(slash_command)]
+async fn verify_files(ctx: AppContext<'_>) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let summary = FileManager::verify(&ctx.data().db_pool).await?;
+
+    let display_files = |files: &[String]| {
+        if files.is_empty() {
+            return "None".to_string();
+        }
+
+        let list = files
+            .iter()
+            .map(format::inline_code)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format::truncate(&list, 1000)
+    };
+
+    let embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("File Verification")
+        .field("Intact", summary.ok.to_string(), true)
+        .field("Missing", summary.missing.len().to_string(), true)
+        .field("Corrupted", summary.corrupted.len().to_string(), true)
+        .field("Unrecorded", summary.unrecorded.len().to_string(), true)
+        .field("Missing Files", display_files(&summary.missing), false)
+        .field("Corrupted Files", display_files(&summary.corrupted), false)
+        .field(
+            "Unrecorded Files",
+            display_files(&summary.unrecorded),
+            false,
+        );
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Find screenshot and evidence files that no report uses and quarantine or delete them.
+#[poise::command(slash_command)]
+async fn collect_garbage(
+    ctx: AppContext<'_>,
+    #[description = "What to do with unused files. Defaults to a dry run that only lists them."]
+    mode: Option<GcMode>,
+    #[description = "Only touch files older than this, e.g. 12h or 7d. Defaults to 1d."]
+    grace_period: Option<String>,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let mode = mode.unwrap_or(GcMode::DryRun);
+
+    let Ok(grace_period) =
+        parse_duration(grace_period.as_deref().unwrap_or(DEFAULT_GC_GRACE_PERIOD))
+    else {
+        ctx.say("Invalid grace period. Use a number followed by m, h, d or w, e.g. 12h or 7d.")
+            .await?;
+        return Ok(());
+    };
+
+    let Some(cutoff) = Utc::now().checked_sub_signed(grace_period) else {
+        ctx.say("The grace period is too long.").await?;
+        return Ok(());
+    };
+
+    if mode != GcMode::DryRun && !FileManager::can_clean_up() {
+        ctx.say("The screenshot storage has no prefix, so it may contain files that do not belong to Janitor. Set a prefix before quarantining or deleting files.")
+            .await?;
+        return Ok(());
+    }
+
+    let summary = FileManager::collect_garbage(&ctx.data().db_pool, cutoff, mode).await?;
+
+    let orphaned_title = match mode {
+        GcMode::DryRun => "Unused Files (not touched)",
+        GcMode::Quarantine => "Quarantined Files",
+        GcMode::Delete => "Deleted Files",
+    };
+
+    let orphaned = if summary.orphaned.is_empty() {
+        "None".to_string()
+    } else {
+        let list = summary
+            .orphaned
+            .iter()
+            .map(format::inline_code)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format::truncate(&list, 1000)
+    };
+
+    let embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("Garbage Collection")
+        .field("In Use", summary.referenced.to_string(), true)
+        .field("Within Grace Period", summary.recent.to_string(), true)
+        .field("Unused", summary.orphaned.len().to_string(), true)
+        .field(orphaned_title, orphaned, false);
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
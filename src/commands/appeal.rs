@@ -0,0 +1,74 @@
+use crate::database::controllers::appeal_model_controller::AppealModelController;
+use crate::database::controllers::badactor_model_controller::BadActorModelController;
+use crate::moderation::appeal::get_appeal_message;
+use crate::util::{locks, logger::Logger};
+use crate::AppContext;
+
+/// The maximum length of an appeal, so it fits into a single embed field.
+const MAX_APPEAL_LEN: usize = 1000;
+
+/// Appeal your report if you think it was a mistake or you recovered your account.
+#[poise::command(slash_command, dm_only = true)]
+pub async fn appeal(
+    ctx: AppContext<'_>,
+    #[description = "Explain why your report should be deactivated."] content: String,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+
+    if content.chars().count() > MAX_APPEAL_LEN {
+        ctx.say(format!(
+            "Your appeal is too long. Please keep it under {MAX_APPEAL_LEN} characters."
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let db_pool = &ctx.data().db_pool;
+    let _guard = locks::lock_user_id(ctx.author().id).await;
+
+    let Some(bad_actor) = BadActorModelController::get_by_user_id(db_pool, ctx.author().id)
+        .await?
+        .into_iter()
+        .find(|b| b.is_active)
+    else {
+        ctx.say("You do not have an active report, so there is nothing to appeal.")
+            .await?;
+        return Ok(());
+    };
+
+    if AppealModelController::get_open_by_bad_actor_id(db_pool, bad_actor.id)
+        .await?
+        .is_some()
+    {
+        ctx.say("You already have an open appeal. Please wait until the admins have reviewed it.")
+            .await?;
+        return Ok(());
+    }
+
+    let appeal =
+        AppealModelController::create(db_pool, bad_actor.id, ctx.author().id, content).await?;
+
+    let message = get_appeal_message(&appeal, ctx.author(), &bad_actor);
+
+    if let Err(e) = ctx
+        .data()
+        .config
+        .admin_server_log_channel
+        .send_message(ctx, message)
+        .await
+    {
+        let log_msg = format!("Failed to post appeal {} to the admin server", appeal.id);
+        Logger::get().error(ctx, e, log_msg).await;
+
+        ctx.say("Your appeal was saved but could not be forwarded to the admins. They have been notified about the problem.")
+            .await?;
+        return Ok(());
+    }
+
+    ctx.say(
+        "Your appeal was submitted. You will receive a message once the admins have reviewed it.",
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use futures::future;
 use poise::serenity_prelude as serenity;
 use poise::CreateReply;
@@ -8,7 +9,6 @@ use serenity::{
     EditInteractionResponse, PartialGuild, User,
 };
 
-use crate::assert_user_server;
 use crate::broadcast::broadcast_handler;
 use crate::database::controllers::badactor_model_controller::BadActorType;
 use crate::database::controllers::badactor_model_controller::BadActorTypeChoice;
@@ -16,11 +16,23 @@ use crate::database::controllers::badactor_model_controller::BroadcastEmbedOptio
 use crate::database::controllers::badactor_model_controller::{
     BadActor, BadActorModelController, BadActorQueryType, CreateBadActorOptions,
 };
+use crate::database::controllers::badactorevent_model_controller::BadActorEventModelController;
+use crate::database::controllers::evidence_model_controller::{
+    CreateEvidenceOptions, EvidenceModelController,
+};
+use crate::database::controllers::moderationaction_model_controller::{
+    ModerationActionModelController, ModerationSource,
+};
 use crate::database::controllers::scores_model_controller::ScoresModelController;
 use crate::util::embeds::EmbedColor;
+use crate::util::parsing::parse_duration;
 use crate::util::{embeds, format, locks, screenshot};
+use crate::{assert_admin, assert_admin_server, assert_user_server};
 use crate::{AppContext, Logger};
 
+/// Together with the screenshot proof this fills the 10 files Discord allows per message.
+const MAX_EVIDENCE: i64 = 9;
+
 struct CollectorOptions<'a> {
     ctx: AppContext<'a>,
     target_user: &'a User,
@@ -30,6 +42,7 @@ struct CollectorOptions<'a> {
     explanation: Option<String>,
     interaction_guild: PartialGuild,
     reply_handle: ReplyHandle<'a>,
+    expires_at: Option<DateTime<Utc>>,
 }
 
 /// Subcommands for server configs.
@@ -39,12 +52,17 @@ struct CollectorOptions<'a> {
     subcommands(
         "report",
         "deactivate",
+        "reactivate",
         "display",
         "display_latest",
         "display_by_user",
         "add_screenshot",
         "replace_screenshot",
-        "update_explanation"
+        "add_evidence",
+        "remove_evidence",
+        "update_explanation",
+        "history",
+        "actions"
     ),
     subcommand_required
 )]
@@ -63,6 +81,8 @@ pub async fn report(
     >,
     #[description = "If you can't provide a screenshot, please explain what happened here."]
     explanation: Option<String>,
+    #[description = "Deactivate the report automatically after this long, e.g. 12h, 7d or 2w."]
+    duration: Option<String>,
 ) -> anyhow::Result<()> {
     ctx.defer().await?;
 
@@ -80,6 +100,23 @@ pub async fn report(
         return Ok(());
     }
 
+    let Ok(duration) = duration.map(|d| parse_duration(&d)).transpose() else {
+        ctx.say("Invalid duration. Use a number followed by m, h, d or w, e.g. 12h or 7d.")
+            .await?;
+        return Ok(());
+    };
+
+    let expires_at = match duration {
+        Some(duration) => match Utc::now().checked_add_signed(duration) {
+            Some(expires_at) => Some(expires_at),
+            None => {
+                ctx.say("The duration is too long.").await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
     let _guard = locks::lock_user_id(target_user.id).await;
 
     if BadActorModelController::has_active_case(&ctx.data().db_pool, target_user.id).await {
@@ -103,6 +140,7 @@ pub async fn report(
             explanation,
             interaction_guild,
             reply_handle,
+            expires_at,
         };
 
         return handle_collector(options).await;
@@ -145,6 +183,7 @@ pub async fn deactivate(
         report_id,
         explanation,
         ctx.author().id,
+        interaction_guild.id,
     )
     .await?;
 
@@ -181,6 +220,90 @@ pub async fn deactivate(
     Ok(())
 }
 
+/// Reactivate the entry of a bad actor that has been deactivated before.
+#[poise::command(slash_command, guild_only = true)]
+pub async fn reactivate(
+    ctx: AppContext<'_>,
+    #[description = "The ID of the report that you want to reactivate."] report_id: i32,
+    #[description = "Reason for reactivating the report"] explanation: String,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+
+    let Some(interaction_guild) = ctx.partial_guild().await else {
+        ctx.say("This command can only be used in a server!")
+            .await?;
+        return Ok(());
+    };
+
+    assert_user_server!(ctx);
+
+    let Some(old_entry) =
+        BadActorModelController::get_by_id(&ctx.data().db_pool, report_id).await?
+    else {
+        ctx.say("There is no such entry in the database!").await?;
+        return Ok(());
+    };
+
+    if old_entry.is_active {
+        ctx.say("This entry is already active!").await?;
+        return Ok(());
+    }
+
+    let _guard = locks::lock_user_id(old_entry.user_id).await;
+
+    if BadActorModelController::has_active_case(&ctx.data().db_pool, old_entry.user_id).await {
+        ctx.say(format!(
+            "User with ID {} already has an active case!",
+            format::inline_code(old_entry.user_id.to_string())
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let reactivated = BadActorModelController::reactivate(
+        &ctx.data().db_pool,
+        report_id,
+        explanation,
+        ctx.author().id,
+        interaction_guild.id,
+    )
+    .await?;
+
+    let Some(target_user) = reactivated.user(ctx).await else {
+        let log_msg = format!(
+            "User with ID {} does not exist anymore, skipping broadcast",
+            reactivated.user_id
+        );
+        Logger::get().warn(ctx, log_msg).await;
+
+        ctx.say("This user's account no longer exists. The report was reactivated in the database but broadcasting will be skipped.")
+            .await?;
+        return Ok(());
+    };
+
+    let origin_guild_id = interaction_guild.id;
+    let broadcast_options = broadcast_handler::BroadcastOptions {
+        bad_actor: &reactivated,
+        bad_actor_user: &target_user,
+        reporting_user: ctx.author(),
+        broadcast_type: broadcast_handler::BroadcastType::Reactivate,
+        config: &ctx.data().config,
+        db_pool: &ctx.data().db_pool,
+        origin_guild: Some(interaction_guild),
+        origin_guild_id,
+        reporting_bot_id: ctx.framework().bot_id,
+    };
+
+    broadcast_handler::broadcast(&ctx, broadcast_options).await;
+
+    ctx.say(format!(
+        "Successfully reactivated report entry {report_id}."
+    ))
+    .await?;
+
+    Ok(())
+}
+
 /// Display a report based on its report ID.
 #[poise::command(slash_command, guild_only = true)]
 pub async fn display(
@@ -275,15 +398,12 @@ pub async fn add_screenshot(
 
     assert_user_server!(ctx);
 
-    let old_entry = match BadActorModelController::get_by_id(&ctx.data().db_pool, report_id).await?
-    {
+    match BadActorModelController::get_by_id(&ctx.data().db_pool, report_id).await? {
         Some(old) => {
             if old.screenshot_proof.is_some() {
                 ctx.say("This report ID already has a screenshot proof. Please use `/badactor replace_screenshot` if you want to overwrite it.").await?;
                 return Ok(());
             }
-
-            old
         }
         None => {
             ctx.say("There is no entry with this report ID!").await?;
@@ -291,7 +411,8 @@ pub async fn add_screenshot(
         }
     };
 
-    let screenshot_path = match screenshot::FileManager::save(screenshot, old_entry.user_id).await {
+    let screenshot_path = match screenshot::FileManager::save(&ctx.data().db_pool, screenshot).await
+    {
         Ok(path) => path,
         Err(e) => {
             let log_msg = "Failed to save screenshot";
@@ -306,6 +427,7 @@ pub async fn add_screenshot(
         &ctx.data().db_pool,
         report_id,
         ctx.author().id,
+        interaction_guild.id,
         screenshot_path,
     )
     .await?;
@@ -375,9 +497,7 @@ pub async fn replace_screenshot(
         return Ok(());
     };
 
-    screenshot::FileManager::delete(&old_screenshot_path).await?;
-
-    let new_path = match screenshot::FileManager::save(screenshot, old_entry.user_id).await {
+    let new_path = match screenshot::FileManager::save(&ctx.data().db_pool, screenshot).await {
         Ok(path) => path,
         Err(e) => {
             let log_msg = "Failed to save screenshot";
@@ -392,10 +512,19 @@ pub async fn replace_screenshot(
         &ctx.data().db_pool,
         report_id,
         ctx.author().id,
+        interaction_guild.id,
         new_path,
     )
     .await?;
 
+    // the old file is only deleted once no report refers to it anymore
+    // the screenshot was already replaced, a leftover file is cleaned up by the garbage collection
+    if let Err(e) = screenshot::FileManager::delete(&ctx.data().db_pool, &old_screenshot_path).await
+    {
+        let log_msg = format!("Failed to delete old screenshot {old_screenshot_path}");
+        Logger::get().error(ctx, e, log_msg).await;
+    }
+
     let Some(target_user) = updated.user(ctx).await else {
         let log_msg = format!(
             "User with ID {} does not exist anymore, skipping broadcast",
@@ -431,6 +560,142 @@ pub async fn replace_screenshot(
     Ok(())
 }
 
+/// Add a screenshot or text log as additional evidence to a bad actor entry.
+#[poise::command(slash_command, guild_only = true)]
+pub async fn add_evidence(
+    ctx: AppContext<'_>,
+    #[description = "The report ID you want to add the evidence to."] report_id: i32,
+    #[description = "A screenshot (jpeg, png) or text log (txt, log). You can upload a file here."]
+    file: Attachment,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+
+    let Some(interaction_guild) = ctx.partial_guild().await else {
+        ctx.say("This command can only be used in a server!")
+            .await?;
+        return Ok(());
+    };
+
+    assert_user_server!(ctx);
+
+    let db_pool = &ctx.data().db_pool;
+
+    let Some(old_entry) = BadActorModelController::get_by_id(db_pool, report_id).await? else {
+        ctx.say("There is no entry with this report ID!").await?;
+        return Ok(());
+    };
+
+    let _guard = locks::lock_user_id(old_entry.user_id).await;
+
+    if EvidenceModelController::count_by_bad_actor_id(db_pool, report_id).await? >= MAX_EVIDENCE {
+        ctx.say(format!(
+            "This report already has the maximum of {MAX_EVIDENCE} pieces of evidence. Please use `/badactor remove_evidence` first."
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let file_name = match screenshot::FileManager::save_evidence(db_pool, file).await {
+        Ok(file_name) => file_name,
+        Err(e) => {
+            let user_msg = format!("Failed to save evidence: {e}");
+            Logger::get().error(ctx, e, "Failed to save evidence").await;
+
+            ctx.say(user_msg).await?;
+            return Ok(());
+        }
+    };
+
+    let evidence_options = CreateEvidenceOptions {
+        bad_actor_id: report_id,
+        file_name,
+        added_by_user_id: ctx.author().id,
+        guild_id: interaction_guild.id,
+    };
+    let evidence = EvidenceModelController::create(db_pool, evidence_options).await?;
+
+    broadcast_evidence_change(
+        ctx,
+        &old_entry,
+        interaction_guild,
+        broadcast_handler::BroadcastType::AddEvidence,
+    )
+    .await;
+
+    ctx.say(format!(
+        "Successfully added evidence #{} to report entry {report_id}.",
+        evidence.position
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a piece of evidence from a bad actor entry by its position.
+#[poise::command(slash_command, guild_only = true)]
+pub async fn remove_evidence(
+    ctx: AppContext<'_>,
+    #[description = "The report ID you want to remove the evidence from."] report_id: i32,
+    #[description = "The position of the evidence, starting at 1."]
+    #[min = 1]
+    position: i32,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+
+    let Some(interaction_guild) = ctx.partial_guild().await else {
+        ctx.say("This command can only be used in a server!")
+            .await?;
+        return Ok(());
+    };
+
+    assert_user_server!(ctx);
+
+    let db_pool = &ctx.data().db_pool;
+
+    let Some(old_entry) = BadActorModelController::get_by_id(db_pool, report_id).await? else {
+        ctx.say("There is no entry with this report ID!").await?;
+        return Ok(());
+    };
+
+    let _guard = locks::lock_user_id(old_entry.user_id).await;
+
+    let Some(deleted) = EvidenceModelController::delete(
+        db_pool,
+        report_id,
+        position,
+        ctx.author().id,
+        interaction_guild.id,
+    )
+    .await?
+    else {
+        ctx.say(format!(
+            "Report entry {report_id} has no evidence #{position}!"
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    if let Err(e) = screenshot::FileManager::delete(db_pool, &deleted.file_name).await {
+        let log_msg = format!("Failed to delete evidence file {}", deleted.file_name);
+        Logger::get().error(ctx, e, log_msg).await;
+    }
+
+    broadcast_evidence_change(
+        ctx,
+        &old_entry,
+        interaction_guild,
+        broadcast_handler::BroadcastType::RemoveEvidence,
+    )
+    .await;
+
+    ctx.say(format!(
+        "Successfully removed evidence #{position} from report entry {report_id}."
+    ))
+    .await?;
+
+    Ok(())
+}
+
 /// Update the explanation of a bad actor entry by its report ID.
 #[poise::command(slash_command, guild_only = true)]
 pub async fn update_explanation(
@@ -453,6 +718,7 @@ pub async fn update_explanation(
         &ctx.data().db_pool,
         report_id,
         ctx.author().id,
+        interaction_guild.id,
         explanation,
     )
     .await?;
@@ -492,6 +758,159 @@ pub async fn update_explanation(
     Ok(())
 }
 
+/// Display the change history of a report.
+#[poise::command(slash_command, guild_only = true)]
+pub async fn history(
+    ctx: AppContext<'_>,
+    #[description = "The report ID you want to see the history of."] report_id: i32,
+    #[description = "The page of the history you want to see. Defaults to 1."] page: Option<i64>,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+    assert_user_server!(ctx);
+
+    const PAGE_SIZE: i64 = 5;
+
+    let db_pool = &ctx.data().db_pool;
+    let event_count =
+        BadActorEventModelController::count_by_bad_actor_id(db_pool, report_id).await?;
+
+    if event_count == 0 {
+        ctx.say("There is no history for this report ID!").await?;
+        return Ok(());
+    }
+
+    let page_count = (event_count + PAGE_SIZE - 1) / PAGE_SIZE;
+    let page = page.unwrap_or(1);
+
+    if page < 1 || page > page_count {
+        ctx.say(format!(
+            "Page {page} does not exist. The history of report {report_id} has {page_count} page(s)."
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let events = BadActorEventModelController::get_by_bad_actor_id(
+        db_pool,
+        report_id,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE,
+    )
+    .await?;
+
+    let mut embed = embeds::CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title(format!("History of Report {report_id}"))
+        .description(format!(
+            "Page {page} of {page_count} ({event_count} events)"
+        ));
+
+    for event in events {
+        let mut value = format!(
+            "By: <@{}> (`{}`)\nServer: {}\nAt: {}",
+            event.changed_by_user_id,
+            event.changed_by_user_id,
+            format::inline_code(event.guild_id.to_string()),
+            format::time(event.created_at, format::TimestampStyle::ShortDateTime)
+        );
+
+        if let Some(old_value) = event.old_value {
+            value.push_str(&format!("\nOld: {}", format::truncate(&old_value, 300)));
+        }
+
+        if let Some(new_value) = event.new_value {
+            value.push_str(&format!("\nNew: {}", format::truncate(&new_value, 300)));
+        }
+
+        let name = format!("#{} {}", event.id, event.event_type.display_name());
+        embed = embed.field(name, value, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Display which servers took moderation action on a report.
+#[poise::command(slash_command, guild_only = true)]
+pub async fn actions(
+    ctx: AppContext<'_>,
+    #[description = "The report ID you want to see the moderation actions for."] report_id: i32,
+    #[description = "The page of the actions you want to see. Defaults to 1."] page: Option<i64>,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    const PAGE_SIZE: i64 = 10;
+
+    let db_pool = &ctx.data().db_pool;
+    let action_count =
+        ModerationActionModelController::count_by_bad_actor_id(db_pool, report_id).await?;
+
+    if action_count == 0 {
+        ctx.say("No moderation actions were taken for this report ID!")
+            .await?;
+        return Ok(());
+    }
+
+    let page_count = (action_count + PAGE_SIZE - 1) / PAGE_SIZE;
+    let page = page.unwrap_or(1);
+
+    if page < 1 || page > page_count {
+        ctx.say(format!(
+            "Page {page} does not exist. The actions of report {report_id} have {page_count} page(s)."
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let actions = ModerationActionModelController::get_by_bad_actor_id(
+        db_pool,
+        report_id,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE,
+    )
+    .await?;
+
+    let mut embed = embeds::CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title(format!("Moderation Actions for Report {report_id}"))
+        .description(format!(
+            "Page {page} of {page_count} ({action_count} actions)"
+        ));
+
+    for action in actions {
+        let acted_by = match (action.source, action.acting_user_id) {
+            (ModerationSource::Button, Some(user_id)) => {
+                format!("<@{user_id}> (`{user_id}`) using the buttons")
+            }
+            (ModerationSource::Button, None) => String::from("Unknown user using the buttons"),
+            (ModerationSource::Automatic, _) => String::from("Automatic"),
+        };
+
+        let outcome = if action.success {
+            String::from("Success")
+        } else {
+            let error = action.error.as_deref().unwrap_or("Unknown error.");
+            format!("Failed: {}", format::truncate(error, 300))
+        };
+
+        let value = format!(
+            "Server: {}\nBy: {acted_by}\nAt: {}\n{outcome}",
+            format::inline_code(action.guild_id.to_string()),
+            format::time(action.created_at, format::TimestampStyle::ShortDateTime)
+        );
+
+        let name = format!("#{} {}", action.id, action.action);
+        embed = embed.field(name, value, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
 async fn handle_collector(options: CollectorOptions<'_>) -> anyhow::Result<()> {
     let CollectorOptions {
         ctx,
@@ -502,6 +921,7 @@ async fn handle_collector(options: CollectorOptions<'_>) -> anyhow::Result<()> {
         explanation,
         interaction_guild,
         reply_handle,
+        expires_at,
     } = options;
 
     if collector.data.custom_id.as_str() == "cancel" {
@@ -555,6 +975,7 @@ async fn handle_collector(options: CollectorOptions<'_>) -> anyhow::Result<()> {
             explanation,
             updated_by_user_id: ctx.author().id,
             origin_guild_id: interaction_guild.id,
+            expires_at,
         };
 
         let bad_actor = save_bad_actor(ctx, target_user, collector, options).await?;
@@ -589,8 +1010,16 @@ async fn handle_collector(options: CollectorOptions<'_>) -> anyhow::Result<()> {
 
         broadcast_handler::broadcast(&ctx, broadcast_options).await;
 
+        let expiry = match bad_actor.expires_at {
+            Some(expires_at) => format!(
+                " The report expires {}.",
+                format::time(expires_at, format::TimestampStyle::Relative)
+            ),
+            None => String::new(),
+        };
+
         let response = EditInteractionResponse::new().content(format!(
-            "Successfully reported {} to the community!",
+            "Successfully reported {} to the community!{expiry}",
             format::fdisplay(target_user)
         ));
 
@@ -645,7 +1074,7 @@ async fn save_screenshot(
     screenshot: Attachment,
     target_user: &User,
 ) -> anyhow::Result<String> {
-    let save_result = screenshot::FileManager::save(screenshot, target_user.id).await;
+    let save_result = screenshot::FileManager::save(&ctx.data().db_pool, screenshot).await;
 
     match save_result {
         Ok(saved) => {
@@ -701,6 +1130,37 @@ async fn save_bad_actor(
     }
 }
 
+async fn broadcast_evidence_change(
+    ctx: AppContext<'_>,
+    bad_actor: &BadActor,
+    interaction_guild: PartialGuild,
+    broadcast_type: broadcast_handler::BroadcastType,
+) {
+    let Some(target_user) = bad_actor.user(ctx).await else {
+        let log_msg = format!(
+            "User with ID {} does not exist anymore, skipping broadcast",
+            bad_actor.user_id
+        );
+        Logger::get().warn(ctx, log_msg).await;
+        return;
+    };
+
+    let origin_guild_id = interaction_guild.id;
+    let broadcast_options = broadcast_handler::BroadcastOptions {
+        bad_actor,
+        bad_actor_user: &target_user,
+        reporting_user: ctx.author(),
+        broadcast_type,
+        config: &ctx.data().config,
+        db_pool: &ctx.data().db_pool,
+        origin_guild: Some(interaction_guild),
+        origin_guild_id,
+        reporting_bot_id: ctx.framework().bot_id,
+    };
+
+    broadcast_handler::broadcast(&ctx, broadcast_options).await;
+}
+
 /// Returns the [CreateReply] built from the vector of [BadActor]s.
 /// This checks for empty vectors or more than 10 embeds and returns error messages if those conditions are violated.
 async fn construct_embeds_message(
@@ -724,23 +1184,33 @@ async fn construct_embeds_message(
             origin_guild: guild,
             origin_guild_id: b.origin_guild_id,
             report_author: ctx.author(),
+            db_pool: &ctx.data().db_pool,
         };
 
         b.to_broadcast_embed(ctx, embed_options, colour).await
     });
 
     let joined = future::join_all(iter).await;
+
+    // only show the galleries if they fit into the message along with every report
+    let gallery_len = joined.iter().map(|e| e.gallery.len()).sum::<usize>();
+    let show_galleries = joined.len() + gallery_len <= 10;
+
     let mut embeds = Vec::with_capacity(joined.len());
     let mut attachments = Vec::with_capacity(joined.len());
 
-    for (embed, attachment) in joined {
-        embeds.push(embed);
-
-        if let Some(a) = attachment {
-            attachments.push(a);
+    for broadcast_embed in joined {
+        if show_galleries {
+            embeds.extend(broadcast_embed.embeds());
+        } else {
+            embeds.push(broadcast_embed.embed);
         }
+
+        attachments.extend(broadcast_embed.attachments);
     }
 
+    attachments.truncate(10);
+
     CreateReply {
         embeds,
         attachments,
@@ -6,10 +6,20 @@ use crate::assert_user_server;
 use crate::database::controllers::serverconfig_model_controller::{
     ActionLevel, ServerConfigComplete, ServerConfigModelController, UpdateServerConfig,
 };
+use crate::database::controllers::webhook_model_controller::WebhookModelController;
+use crate::honeypot::heuristics::ContentSimilarity;
 use crate::util::logger::Logger;
-use crate::util::parsing::parse_role_ids;
+use crate::util::parsing::{parse_role_ids, parse_types};
 use crate::AppContext;
 
+use super::webhook::{
+    resolve_new_webhook, send_test_message, update_webhook_filter, valid_actor_types,
+    valid_broadcast_types,
+};
+
+/// The longest timeout Discord allows, 28 days.
+const MAX_TIMEOUT_MINUTES: i32 = 28 * 24 * 60;
+
 /// Subcommands for server configs.
 #[poise::command(
     slash_command,
@@ -19,7 +29,10 @@ use crate::AppContext;
         "update",
         "enable_honeypot",
         "disable_honeypot",
-        "honeypot_message"
+        "honeypot_message",
+        "set_webhook",
+        "remove_webhook",
+        "webhook_filter"
     ),
     subcommand_required
 )]
@@ -69,12 +82,38 @@ async fn update(
     bigotry_action_level: Option<ActionLevel>,
     #[description = "The level of action to take for users reported through honeypots."]
     honeypot_action_level: Option<ActionLevel>,
+    #[description = "Minutes to time out spamming users for with the timeout action level."]
+    spam_action_timeout: Option<i32>,
+    #[description = "Minutes to time out impersonating users for with the timeout action level."]
+    impersonation_action_timeout: Option<i32>,
+    #[description = "Minutes to time out bigoted users for with the timeout action level."]
+    bigotry_action_timeout: Option<i32>,
+    #[description = "Minutes to time out users reported through honeypots for with the timeout action level."]
+    honeypot_action_timeout: Option<i32>,
     #[description = "Role IDs to ignore when taking action. Separate multiple with a comma (,)."]
     ignored_roles: Option<String>,
     #[description = "Custom ban reason for automatic bans. Add {id} and/or {type} to show them in your reason."]
     ban_reason: Option<String>,
+    #[description = "Days of messages to delete when banning or softbanning users. Between 0 and 7."]
+    ban_delete_message_days: Option<i32>,
     #[description = "Timeout users who send messages in your honeypot channel in Minutes. 0 to turn off."]
     honeypot_timeout: Option<i32>,
+    #[description = "Seconds to remember messages for to detect spam in your honeypot. Between 10 and 600."]
+    honeypot_window: Option<i32>,
+    #[description = "Distinct channels the same message has to be posted in to be reported. Between 2 and 10."]
+    honeypot_min_channels: Option<i32>,
+    #[description = "Report users for a single message in your honeypot channel."]
+    honeypot_single_hit: Option<bool>,
+    #[description = "How messages are compared to detect spam in your honeypot."]
+    honeypot_similarity: Option<ContentSimilarity>,
+    #[description = "Report users who post the same message in multiple servers that use Janitor."]
+    cross_server_detection: Option<bool>,
+    #[description = "Bad actor types to post in your log channel, e.g. spam, bigotry. Use all for every type."]
+    log_actor_types: Option<String>,
+    #[description = "Broadcast types to post in your log channel, e.g. report, honeypot. Use all for every type."]
+    log_broadcast_types: Option<String>,
+    #[description = "Unban users Janitor banned in your server when their report is deactivated."]
+    auto_unban_on_deactivate: Option<bool>,
 ) -> anyhow::Result<()> {
     ctx.defer().await?;
     assert_user_server!(ctx);
@@ -116,8 +155,74 @@ async fn update(
         None
     };
 
+    if let Some(days) = ban_delete_message_days {
+        if !(0..=7).contains(&days) {
+            ctx.say(format!(
+                "The days of messages to delete on ban have to be between 0 and 7, got {days}!"
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
     let honeypot_timeout_minutes = honeypot_timeout.unwrap_or(0);
 
+    let action_timeouts = [
+        spam_action_timeout,
+        impersonation_action_timeout,
+        bigotry_action_timeout,
+        honeypot_action_timeout,
+    ];
+    if let Some(minutes) = action_timeouts
+        .into_iter()
+        .flatten()
+        .find(|minutes| !(1..=MAX_TIMEOUT_MINUTES).contains(minutes))
+    {
+        ctx.say(format!(
+            "Timeouts have to be between 1 and {MAX_TIMEOUT_MINUTES} minutes (28 days), got {minutes}!"
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(window) = honeypot_window {
+        if !(10..=600).contains(&window) {
+            ctx.say(format!(
+                "The honeypot window has to be between 10 and 600 seconds, got {window}!"
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    if let Some(min_channels) = honeypot_min_channels {
+        if !(2..=10).contains(&min_channels) {
+            ctx.say(format!(
+                "The minimum channel count has to be between 2 and 10, got {min_channels}!"
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let Ok(log_actor_types) = log_actor_types.map(|t| parse_types(&t)).transpose() else {
+        ctx.say(format!(
+            "Invalid bad actor types. Valid types are: {}.",
+            valid_actor_types()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let Ok(log_broadcast_types) = log_broadcast_types.map(|t| parse_types(&t)).transpose() else {
+        ctx.say(format!(
+            "Invalid broadcast types. Valid types are: {}.",
+            valid_broadcast_types()
+        ))
+        .await?;
+        return Ok(());
+    };
+
     let update_values = UpdateServerConfig {
         log_channel_id,
         ping_users,
@@ -129,11 +234,28 @@ async fn update(
         ignored_roles,
         ban_reason,
         honeypot_timeout_minutes,
+        honeypot_window_seconds: honeypot_window,
+        honeypot_min_channels,
+        honeypot_single_hit,
+        honeypot_similarity,
+        cross_server_detection,
+        log_actor_types,
+        log_broadcast_types,
+        auto_unban_on_deactivate,
+        spam_action_timeout_minutes: spam_action_timeout,
+        impersonation_action_timeout_minutes: impersonation_action_timeout,
+        bigotry_action_timeout_minutes: bigotry_action_timeout,
+        honeypot_action_timeout_minutes: honeypot_action_timeout,
+        ban_delete_message_days,
     };
 
     let updated =
         ServerConfigModelController::update(&ctx.data().db_pool, guild_id, update_values).await?;
 
+    ctx.data()
+        .honeypot_heuristics
+        .insert(guild_id, updated.honeypot_heuristics());
+
     let embed = ServerConfigComplete::try_from_server_config(updated, &ctx.data().db_pool, &ctx)
         .await?
         .to_embed(ctx.author());
@@ -234,6 +356,74 @@ async fn honeypot_message(ctx: AppContext<'_>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Receive broadcasts through a webhook in your server, for example to forward them outside of Discord.
+#[poise::command(slash_command, guild_only = true)]
+async fn set_webhook(
+    ctx: AppContext<'_>,
+    #[description = "The URL of a webhook in this server."] url: String,
+) -> anyhow::Result<()> {
+    ctx.defer_ephemeral().await?;
+    assert_user_server!(ctx);
+
+    // SAFETY: assert_user_server!() returns if guild_id is None
+    let guild_id = ctx.guild_id().unwrap();
+
+    let Some(webhook) = resolve_new_webhook(ctx, &url).await? else {
+        return Ok(());
+    };
+
+    if webhook.guild_id != guild_id {
+        ctx.say("The webhook has to belong to this server.").await?;
+        return Ok(());
+    }
+
+    if !send_test_message(ctx, &webhook).await? {
+        return Ok(());
+    }
+
+    WebhookModelController::upsert(&ctx.data().db_pool, &webhook).await?;
+
+    ctx.say("Successfully set the webhook for your server. A test message was sent to it.")
+        .await?;
+    Ok(())
+}
+
+/// Stop receiving broadcasts through the webhook in your server.
+#[poise::command(slash_command, guild_only = true)]
+async fn remove_webhook(ctx: AppContext<'_>) -> anyhow::Result<()> {
+    ctx.defer().await?;
+    assert_user_server!(ctx);
+
+    // SAFETY: assert_user_server!() returns if guild_id is None
+    let guild_id = ctx.guild_id().unwrap();
+
+    let reply = match WebhookModelController::delete(&ctx.data().db_pool, guild_id).await? {
+        Some(_) => "Successfully removed the webhook for your server.",
+        None => "Your server doesn't have a webhook.",
+    };
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Choose which broadcasts the webhook in your server receives.
+#[poise::command(slash_command, guild_only = true)]
+async fn webhook_filter(
+    ctx: AppContext<'_>,
+    #[description = "Bad actor types to receive, e.g. spam, bigotry. Use all for every type."]
+    actor_types: Option<String>,
+    #[description = "Broadcast types to receive, e.g. report, honeypot. Use all for every type."]
+    broadcast_types: Option<String>,
+) -> anyhow::Result<()> {
+    ctx.defer().await?;
+    assert_user_server!(ctx);
+
+    // SAFETY: assert_user_server!() returns if guild_id is None
+    let guild_id = ctx.guild_id().unwrap();
+
+    update_webhook_filter(ctx, guild_id, actor_types, broadcast_types).await
+}
+
 fn check_ban_reason(ban_reason: &str) -> bool {
     let mut brace_count = 0;
 
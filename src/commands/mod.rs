@@ -1,6 +1,8 @@
 pub mod adminconfig;
 pub mod adminlist;
+pub mod appeal;
 pub mod badactor;
 pub mod config;
 pub mod scores;
 pub mod user;
+pub mod webhook;
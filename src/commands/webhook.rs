@@ -0,0 +1,294 @@
+use std::fmt::Display;
+
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use serenity::{ExecuteWebhook, GuildId, Webhook};
+use url::Url;
+
+use crate::broadcast::broadcast_handler::BroadcastType;
+use crate::broadcast::filter::BroadcastFilter;
+use crate::broadcast::webhooks::{DiscordWebhookSink, WebhookSink};
+use crate::database::controllers::badactor_model_controller::BadActorType;
+use crate::database::controllers::webhook_model_controller::{
+    BroadcastWebhook, WebhookModelController,
+};
+use crate::util::embeds::CreateJanitorEmbed;
+use crate::util::format;
+use crate::util::parsing::parse_types;
+use crate::AppContext;
+use crate::{assert_admin, assert_admin_server};
+
+/// The maximum length of the `guild_name` column in the `webhooks` table.
+const MAX_GUILD_NAME_LEN: usize = 50;
+
+/// The bad actor types the filter options accept, e.g. `spam, impersonation`.
+pub fn valid_actor_types() -> String {
+    join_types(&BadActorType::ALL)
+}
+
+/// The broadcast types the filter options accept, e.g. `report, deactivate`.
+pub fn valid_broadcast_types() -> String {
+    join_types(&BroadcastType::ALL)
+}
+
+fn join_types<T: Display>(types: &[T]) -> String {
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Subcommands for admins to manage the webhooks broadcasts are sent to.
+#[poise::command(
+    slash_command,
+    guild_only = true,
+    subcommands("add", "remove", "list", "test", "filter"),
+    subcommand_required
+)]
+pub async fn webhook(_: AppContext<'_>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Add a webhook to broadcast to. Replaces the existing webhook of that server.
+#[poise::command(slash_command, guild_only = true)]
+async fn add(
+    ctx: AppContext<'_>,
+    #[description = "The URL of the webhook."] url: String,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer_ephemeral().await?;
+
+    let Some(webhook) = resolve_new_webhook(ctx, &url).await? else {
+        return Ok(());
+    };
+
+    if !send_test_message(ctx, &webhook).await? {
+        return Ok(());
+    }
+
+    let webhook = WebhookModelController::upsert(&ctx.data().db_pool, &webhook).await?;
+
+    let reply = format!(
+        "Successfully added the webhook for {} (`{}`). A test message was sent to it.",
+        webhook.guild_name, webhook.guild_id
+    );
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Remove the webhook of a server.
+#[poise::command(slash_command, guild_only = true)]
+async fn remove(
+    ctx: AppContext<'_>,
+    #[description = "The ID of the server to remove the webhook for."] server_id: GuildId,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let reply = match WebhookModelController::delete(&ctx.data().db_pool, server_id).await? {
+        Some(webhook) => format!(
+            "Successfully removed the webhook for {} (`{}`).",
+            webhook.guild_name, webhook.guild_id
+        ),
+        None => format!("There is no webhook for server `{server_id}`."),
+    };
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// List all servers that have a webhook to broadcast to.
+#[poise::command(slash_command, guild_only = true)]
+async fn list(ctx: AppContext<'_>) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let webhooks = WebhookModelController::get_all(&ctx.data().db_pool).await?;
+
+    if webhooks.is_empty() {
+        ctx.say("There are no webhooks to broadcast to.").await?;
+        return Ok(());
+    }
+
+    // the URLs contain the webhook tokens, so only the servers are displayed
+    let description = webhooks
+        .iter()
+        .map(|w| format!("{} (`{}`)\n{}", w.guild_name, w.guild_id, w.filter))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("Servers with Webhooks")
+        .description(format::truncate(&description, 4096));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Send a test message to the webhook of a server.
+#[poise::command(slash_command, guild_only = true)]
+async fn test(
+    ctx: AppContext<'_>,
+    #[description = "The ID of the server to test the webhook for."] server_id: GuildId,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    let Some(webhook) =
+        WebhookModelController::get_by_guild_id(&ctx.data().db_pool, server_id).await?
+    else {
+        ctx.say(format!("There is no webhook for server `{server_id}`."))
+            .await?;
+        return Ok(());
+    };
+
+    if send_test_message(ctx, &webhook).await? {
+        let reply = format!(
+            "Successfully sent a test message to the webhook for {} (`{}`).",
+            webhook.guild_name, webhook.guild_id
+        );
+        ctx.say(reply).await?;
+    }
+
+    Ok(())
+}
+
+/// Choose which broadcasts the webhook of a server receives.
+#[poise::command(slash_command, guild_only = true)]
+async fn filter(
+    ctx: AppContext<'_>,
+    #[description = "The ID of the server to filter the webhook for."] server_id: GuildId,
+    #[description = "Bad actor types to receive, e.g. spam, bigotry. Use all for every type."]
+    actor_types: Option<String>,
+    #[description = "Broadcast types to receive, e.g. report, honeypot. Use all for every type."]
+    broadcast_types: Option<String>,
+) -> anyhow::Result<()> {
+    assert_admin!(ctx);
+    assert_admin_server!(ctx);
+    ctx.defer().await?;
+
+    update_webhook_filter(ctx, server_id, actor_types, broadcast_types).await
+}
+
+/// Updates the parts of the webhook filter that were given and tells the user about the new filter.
+pub async fn update_webhook_filter(
+    ctx: AppContext<'_>,
+    guild_id: GuildId,
+    actor_types: Option<String>,
+    broadcast_types: Option<String>,
+) -> anyhow::Result<()> {
+    let db_pool = &ctx.data().db_pool;
+
+    let Some(webhook) = WebhookModelController::get_by_guild_id(db_pool, guild_id).await? else {
+        ctx.say(format!("There is no webhook for server `{guild_id}`."))
+            .await?;
+        return Ok(());
+    };
+
+    let Ok(actor_types) = actor_types.map(|t| parse_types(&t)).transpose() else {
+        ctx.say(format!(
+            "Invalid bad actor types. Valid types are: {}.",
+            valid_actor_types()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let Ok(broadcast_types) = broadcast_types.map(|t| parse_types(&t)).transpose() else {
+        ctx.say(format!(
+            "Invalid broadcast types. Valid types are: {}.",
+            valid_broadcast_types()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let filter = BroadcastFilter {
+        actor_types: actor_types.unwrap_or(webhook.filter.actor_types),
+        broadcast_types: broadcast_types.unwrap_or(webhook.filter.broadcast_types),
+    };
+
+    let Some(webhook) = WebhookModelController::update_filter(db_pool, guild_id, &filter).await?
+    else {
+        ctx.say(format!("There is no webhook for server `{guild_id}`."))
+            .await?;
+        return Ok(());
+    };
+
+    let reply = format!(
+        "Successfully updated the webhook filter for {} (`{}`).\n{}",
+        webhook.guild_name, webhook.guild_id, webhook.filter
+    );
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Checks that the URL belongs to a Discord webhook in a server and gets the name of that server.
+/// Tells the user what is wrong and returns `None` if the webhook can't be used.
+pub async fn resolve_new_webhook(
+    ctx: AppContext<'_>,
+    url: &str,
+) -> anyhow::Result<Option<BroadcastWebhook>> {
+    let Ok(webhook_url) = Url::parse(url) else {
+        ctx.say("That is not a valid URL.").await?;
+        return Ok(None);
+    };
+
+    let Ok(discord_webhook) = Webhook::from_url(ctx.http(), webhook_url.as_str()).await else {
+        ctx.say("Failed to connect to the webhook. Make sure the URL is correct.")
+            .await?;
+        return Ok(None);
+    };
+
+    let Some(guild_id) = discord_webhook.guild_id else {
+        ctx.say("This webhook does not belong to a server.").await?;
+        return Ok(None);
+    };
+
+    // the bot does not have to be in the server the webhook posts to
+    let guild_name = match guild_id.to_partial_guild(ctx).await {
+        Ok(guild) => guild.name,
+        Err(_) => discord_webhook
+            .name
+            .clone()
+            .unwrap_or_else(|| guild_id.to_string()),
+    };
+
+    Ok(Some(BroadcastWebhook {
+        guild_id,
+        guild_name: format::truncate(&guild_name, MAX_GUILD_NAME_LEN),
+        webhook_url,
+        filter: BroadcastFilter::default(),
+    }))
+}
+
+/// Sends a test message to the webhook. Tells the user and returns `false` if that failed.
+pub async fn send_test_message(
+    ctx: AppContext<'_>,
+    webhook: &BroadcastWebhook,
+) -> anyhow::Result<bool> {
+    let embed = CreateJanitorEmbed::new(ctx.author())
+        .into_embed()
+        .title("Janitor Webhook Test")
+        .description("This webhook will receive broadcasts about bad actors from Janitor.");
+
+    let sink = DiscordWebhookSink { http: ctx.http() };
+
+    let execute = ExecuteWebhook::default().embed(embed);
+
+    if let Err(e) = sink.execute(webhook, execute).await {
+        ctx.say(format!("Failed to send a test message to the webhook: {e}"))
+            .await?;
+        return Ok(false);
+    }
+
+    Ok(true)
+}
@@ -17,6 +17,7 @@ struct DbAdmin {
 #[derive(Debug)]
 pub struct Admin {
     pub user_id: UserId,
+    #[allow(dead_code)]
     pub created_at: DateTime<Utc>,
 }
 
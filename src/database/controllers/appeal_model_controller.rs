@@ -0,0 +1,164 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::UserId;
+use sqlx::{FromRow, PgPool};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AppealStatus {
+    Open,
+    Accepted,
+    Rejected,
+}
+
+impl Display for AppealStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open => write!(f, "open"),
+            Self::Accepted => write!(f, "accepted"),
+            Self::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl FromStr for AppealStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "accepted" => Ok(Self::Accepted),
+            "rejected" => Ok(Self::Rejected),
+            _ => anyhow::bail!("Invalid appeal status: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct DbAppeal {
+    id: i32,
+    bad_actor_id: i32,
+    user_id: String,
+    content: String,
+    status: String,
+    resolved_by_user_id: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct Appeal {
+    pub id: i32,
+    pub bad_actor_id: i32,
+    pub user_id: UserId,
+    pub content: String,
+    pub status: AppealStatus,
+    #[allow(dead_code)]
+    pub resolved_by_user_id: Option<UserId>,
+    pub created_at: DateTime<Utc>,
+    #[allow(dead_code)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbAppeal> for Appeal {
+    type Error = anyhow::Error;
+
+    fn try_from(db_appeal: DbAppeal) -> Result<Self, Self::Error> {
+        Ok(Appeal {
+            id: db_appeal.id,
+            bad_actor_id: db_appeal.bad_actor_id,
+            user_id: UserId::from_str(&db_appeal.user_id)?,
+            content: db_appeal.content,
+            status: AppealStatus::from_str(&db_appeal.status)?,
+            resolved_by_user_id: db_appeal
+                .resolved_by_user_id
+                .map(|id| UserId::from_str(&id))
+                .transpose()?,
+            created_at: db_appeal.created_at.and_utc(),
+            updated_at: db_appeal.updated_at.and_utc(),
+        })
+    }
+}
+
+pub struct AppealModelController;
+
+impl AppealModelController {
+    /// Submit a new open appeal against a bad actor entry.
+    pub async fn create(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+        user_id: UserId,
+        content: impl Into<String>,
+    ) -> anyhow::Result<Appeal> {
+        let db_appeal = sqlx::query_as::<_, DbAppeal>(
+            r#"
+            INSERT INTO appeals (bad_actor_id, user_id, content)
+            VALUES ($1, $2, $3)
+            RETURNING *;
+            "#,
+        )
+        .bind(bad_actor_id)
+        .bind(user_id.to_string())
+        .bind(content.into())
+        .fetch_one(db_pool)
+        .await?;
+
+        db_appeal.try_into()
+    }
+
+    /// Get the open appeal against a bad actor entry, if there is one.
+    pub async fn get_open_by_bad_actor_id(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+    ) -> anyhow::Result<Option<Appeal>> {
+        let db_appeal = sqlx::query_as::<_, DbAppeal>(
+            "SELECT * FROM appeals WHERE bad_actor_id = $1 AND status = 'open';",
+        )
+        .bind(bad_actor_id)
+        .fetch_optional(db_pool)
+        .await?;
+
+        db_appeal.map(Appeal::try_from).transpose()
+    }
+
+    /// Get an appeal by its ID if it is still open.
+    pub async fn get_open_by_id(db_pool: &PgPool, id: i32) -> anyhow::Result<Option<Appeal>> {
+        let db_appeal = sqlx::query_as::<_, DbAppeal>(
+            "SELECT * FROM appeals WHERE id = $1 AND status = 'open';",
+        )
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?;
+
+        db_appeal.map(Appeal::try_from).transpose()
+    }
+
+    /// Accept or reject an open appeal. Returns `None` if there is no such appeal or it was already resolved.
+    pub async fn resolve(
+        db_pool: &PgPool,
+        id: i32,
+        status: AppealStatus,
+        resolved_by_user_id: UserId,
+    ) -> anyhow::Result<Option<Appeal>> {
+        let db_appeal = sqlx::query_as::<_, DbAppeal>(
+            r#"
+            UPDATE appeals
+            SET
+                status = $2,
+                resolved_by_user_id = $3,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND status = 'open'
+            RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .bind(status.to_string())
+        .bind(resolved_by_user_id.to_string())
+        .fetch_optional(db_pool)
+        .await?;
+
+        db_appeal.map(Appeal::try_from).transpose()
+    }
+}
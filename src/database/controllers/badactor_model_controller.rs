@@ -7,13 +7,24 @@ use serenity::{
     CacheHttp, CreateAttachment, CreateEmbed, CreateEmbedFooter, GuildId, Mentionable,
     PartialGuild, User as SerenityUser, User, UserId,
 };
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, PgConnection, PgPool};
 
+use crate::database::controllers::badactorevent_model_controller::{
+    BadActorEventModelController, BadActorEventType, CreateBadActorEventOptions,
+};
+use crate::database::controllers::evidence_model_controller::EvidenceModelController;
 use crate::util::embeds::EmbedColor;
 use crate::util::{format, screenshot};
 use crate::Logger;
 
-#[derive(Debug, Copy, Clone)]
+/// Discord shows at most this many images in a gallery.
+const MAX_GALLERY_IMAGES: usize = 4;
+/// Discord allows at most this many files per message.
+const MAX_ATTACHMENTS: usize = 10;
+/// Discord's upload limit for a single message in bytes.
+const MAX_ATTACHMENTS_SIZE: usize = 10_000_000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BadActorType {
     Spam,
     Impersonation,
@@ -21,6 +32,15 @@ pub enum BadActorType {
     Honeypot,
 }
 
+impl BadActorType {
+    pub const ALL: [Self; 4] = [
+        Self::Spam,
+        Self::Impersonation,
+        Self::Bigotry,
+        Self::Honeypot,
+    ];
+}
+
 impl Display for BadActorType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -65,6 +85,7 @@ struct DbBadActor {
     created_at: NaiveDateTime,
     updated_at: NaiveDateTime,
     last_changed_by: String,
+    expires_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug)]
@@ -76,9 +97,14 @@ pub struct BadActor {
     pub origin_guild_id: GuildId,
     pub screenshot_proof: Option<String>,
     pub explanation: Option<String>,
+    #[allow(dead_code)]
     pub created_at: DateTime<Utc>,
+    #[allow(dead_code)]
     pub updated_at: DateTime<Utc>,
+    #[allow(dead_code)]
     pub updated_by_user_id: UserId,
+    /// When the report deactivates itself. `None` for reports that never expire.
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug)]
@@ -87,6 +113,26 @@ pub struct BroadcastEmbedOptions<'a> {
     pub origin_guild: Option<PartialGuild>,
     pub report_author: &'a User,
     pub bot_id: UserId,
+    pub db_pool: &'a PgPool,
+}
+
+/// The embeds and files a bad actor is broadcasted with.
+#[derive(Debug, Clone)]
+pub struct BroadcastEmbed {
+    /// The report itself, which is always the first embed of the message.
+    pub embed: CreateEmbed,
+    /// Image-only embeds that Discord shows as a gallery together with the report.
+    pub gallery: Vec<CreateEmbed>,
+    /// The screenshot proof followed by the evidence.
+    pub attachments: Vec<CreateAttachment>,
+}
+
+impl BroadcastEmbed {
+    pub fn embeds(&self) -> Vec<CreateEmbed> {
+        std::iter::once(self.embed.clone())
+            .chain(self.gallery.iter().cloned())
+            .collect()
+    }
 }
 
 impl BadActor {
@@ -100,12 +146,13 @@ impl BadActor {
         cache_http: impl CacheHttp,
         options: BroadcastEmbedOptions<'a>,
         colour: EmbedColor,
-    ) -> (CreateEmbed, Option<CreateAttachment>) {
+    ) -> BroadcastEmbed {
         let BroadcastEmbedOptions {
             origin_guild_id,
             origin_guild,
             report_author,
             bot_id,
+            db_pool,
         } = options;
 
         let explanation = self
@@ -172,19 +219,91 @@ impl BadActor {
             }
         };
 
-        let attachment = match self.screenshot_proof.clone() {
-            Some(path) => screenshot::FileManager::get(&path).await.ok(),
-            None => None,
+        let attachments = self.get_attachments(&cache_http, db_pool).await;
+
+        let mut images = attachments
+            .iter()
+            .filter(|a| screenshot::is_image(&a.filename))
+            .take(MAX_GALLERY_IMAGES)
+            .map(|a| format!("attachment://{}", a.filename));
+
+        let Some(first_image) = images.next() else {
+            return BroadcastEmbed {
+                embed,
+                gallery: Vec::new(),
+                attachments,
+            };
         };
 
-        match attachment {
-            Some(attachment) => {
-                let embed = embed.image(format!("attachment://{}", attachment.filename));
+        let embed = embed.image(first_image);
+
+        // Discord groups embeds with the same url into a gallery
+        let gallery = images
+            .map(|image| CreateEmbed::default().url(self.gallery_url()).image(image))
+            .collect::<Vec<_>>();
 
-                (embed, Some(attachment))
+        let embed = if gallery.is_empty() {
+            embed
+        } else {
+            embed.url(self.gallery_url())
+        };
+
+        BroadcastEmbed {
+            embed,
+            gallery,
+            attachments,
+        }
+    }
+
+    fn gallery_url(&self) -> String {
+        format!("https://discord.com/users/{}", self.user_id)
+    }
+
+    /// Loads the screenshot proof and the evidence of the entry.
+    /// Files that cannot be loaded or do not fit into a single message are skipped.
+    async fn get_attachments(
+        &self,
+        cache_http: impl CacheHttp,
+        db_pool: &PgPool,
+    ) -> Vec<CreateAttachment> {
+        let evidence = match EvidenceModelController::get_by_bad_actor_id(db_pool, self.id).await {
+            Ok(evidence) => evidence,
+            Err(e) => {
+                let log_msg = format!("Failed to get the evidence of report {}", self.id);
+                Logger::get().error(&cache_http, e, log_msg).await;
+                Vec::new()
             }
-            None => (embed, None),
+        };
+
+        let file_names = self
+            .screenshot_proof
+            .iter()
+            .cloned()
+            .chain(evidence.into_iter().map(|e| e.file_name));
+
+        let mut attachments = Vec::new();
+        let mut total_size = 0;
+
+        for file_name in file_names {
+            let Ok(attachment) = screenshot::FileManager::get(&file_name).await else {
+                continue;
+            };
+
+            if attachments.len() >= MAX_ATTACHMENTS
+                || total_size + attachment.data.len() > MAX_ATTACHMENTS_SIZE
+            {
+                tracing::warn!(
+                    "Skipping {file_name} of report {} because it does not fit into the broadcast.",
+                    self.id
+                );
+                continue;
+            }
+
+            total_size += attachment.data.len();
+            attachments.push(attachment);
         }
+
+        attachments
     }
 
     pub fn ban_reason(&self, custom_reason: Option<String>) -> String {
@@ -209,6 +328,7 @@ impl TryFrom<DbBadActor> for BadActor {
             explanation,
             created_at,
             updated_at,
+            expires_at,
             ..
         } = db_bad_actor;
 
@@ -219,6 +339,7 @@ impl TryFrom<DbBadActor> for BadActor {
 
         let created_at = created_at.and_utc();
         let updated_at = updated_at.and_utc();
+        let expires_at = expires_at.map(|e| e.and_utc());
 
         let bad_actor = BadActor {
             id,
@@ -231,6 +352,7 @@ impl TryFrom<DbBadActor> for BadActor {
             updated_at,
             origin_guild_id,
             updated_by_user_id,
+            expires_at,
         };
 
         Ok(bad_actor)
@@ -244,6 +366,7 @@ pub struct CreateBadActorOptions {
     pub explanation: Option<String>,
     pub origin_guild_id: GuildId,
     pub updated_by_user_id: UserId,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, poise::ChoiceParameter)]
@@ -268,12 +391,15 @@ impl BadActorModelController {
             explanation,
             origin_guild_id,
             updated_by_user_id,
+            expires_at,
         } = options;
 
-        sqlx::query_as::<_, DbBadActor>(
+        let mut tx = db_pool.begin().await?;
+
+        let db_bad_actor = sqlx::query_as::<_, DbBadActor>(
             r#"
-            INSERT INTO bad_actors (user_id, actor_type, originally_created_in, screenshot_proof, explanation, last_changed_by)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO bad_actors (user_id, actor_type, originally_created_in, screenshot_proof, explanation, last_changed_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING *;
             "#,
         )
@@ -283,9 +409,23 @@ impl BadActorModelController {
         .bind(screenshot_proof)
         .bind(explanation)
         .bind(updated_by_user_id.to_string())
-        .fetch_one(db_pool)
-        .await?
-        .try_into()
+        .bind(expires_at.map(|e| e.naive_utc()))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: db_bad_actor.id,
+            event_type: BadActorEventType::Created,
+            old_value: None,
+            new_value: db_bad_actor.explanation.clone(),
+            changed_by_user_id: updated_by_user_id,
+            guild_id: origin_guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
+        db_bad_actor.try_into()
     }
 
     /// Returns if the given user ID currently has an active case.
@@ -335,7 +475,11 @@ impl BadActorModelController {
         id: i32,
         explanation: impl Into<String>,
         updated_by_user_id: UserId,
+        guild_id: GuildId,
     ) -> anyhow::Result<BadActor> {
+        let mut tx = db_pool.begin().await?;
+        let previous = Self::get_for_update(&mut tx, id).await?;
+
         let updated_db_bad_actor = sqlx::query_as::<_, DbBadActor>(
             r#"
             UPDATE bad_actors
@@ -351,12 +495,83 @@ impl BadActorModelController {
         .bind(id)
         .bind(explanation.into())
         .bind(updated_by_user_id.to_string())
-        .fetch_one(db_pool)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: id,
+            event_type: BadActorEventType::Deactivated,
+            old_value: previous.explanation,
+            new_value: updated_db_bad_actor.explanation.clone(),
+            changed_by_user_id: updated_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
+        updated_db_bad_actor.try_into()
+    }
+
+    /// Reactivate a previously deactivated bad actor entry by its unique ID with the given explanation.
+    /// This also updates the `updated_by_user_id` field to the user ID of the user who reactivated the entry.
+    /// Reactivated entries never expire.
+    pub async fn reactivate(
+        db_pool: &PgPool,
+        id: i32,
+        explanation: impl Into<String>,
+        updated_by_user_id: UserId,
+        guild_id: GuildId,
+    ) -> anyhow::Result<BadActor> {
+        let mut tx = db_pool.begin().await?;
+        let previous = Self::get_for_update(&mut tx, id).await?;
+
+        let updated_db_bad_actor = sqlx::query_as::<_, DbBadActor>(
+            r#"
+            UPDATE bad_actors
+            SET
+                is_active = true,
+                expires_at = NULL,
+                explanation = $2,
+                last_changed_by = $3,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .bind(explanation.into())
+        .bind(updated_by_user_id.to_string())
+        .fetch_one(&mut *tx)
         .await?;
 
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: id,
+            event_type: BadActorEventType::Reactivated,
+            old_value: previous.explanation,
+            new_value: updated_db_bad_actor.explanation.clone(),
+            changed_by_user_id: updated_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
         updated_db_bad_actor.try_into()
     }
 
+    /// Get all active entries whose expiry has passed.
+    pub async fn get_expired(db_pool: &PgPool) -> anyhow::Result<Vec<BadActor>> {
+        sqlx::query_as::<_, DbBadActor>(
+            "SELECT * FROM bad_actors WHERE is_active = true AND expires_at <= CURRENT_TIMESTAMP ORDER BY expires_at;",
+        )
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(BadActor::try_from)
+        .collect::<Result<Vec<BadActor>, _>>()
+    }
+
     /// Get the most recent bad actor entries with the given limit and query type. Defaults to `BadActorQueryType::All`.
     pub async fn get_by_type(
         db_pool: &PgPool,
@@ -384,13 +599,33 @@ impl BadActorModelController {
             .collect::<Result<Vec<BadActor>, _>>()
     }
 
-    pub async fn delete(pg_pool: &PgPool, id: i32) -> anyhow::Result<BadActor> {
+    /// Delete a bad actor entry by its unique ID. The history of the entry is kept.
+    pub async fn delete(
+        pg_pool: &PgPool,
+        id: i32,
+        deleted_by_user_id: UserId,
+        guild_id: GuildId,
+    ) -> anyhow::Result<BadActor> {
+        let mut tx = pg_pool.begin().await?;
+
         let deleted_db_bad_actor =
             sqlx::query_as::<_, DbBadActor>("DELETE FROM bad_actors WHERE id = $1 RETURNING *;")
                 .bind(id)
-                .fetch_one(pg_pool)
+                .fetch_one(&mut *tx)
                 .await?;
 
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: id,
+            event_type: BadActorEventType::Deleted,
+            old_value: deleted_db_bad_actor.explanation.clone(),
+            new_value: None,
+            changed_by_user_id: deleted_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
         tracing::info!("Deleted bad actor entry with ID {id} from the database.");
 
         deleted_db_bad_actor.try_into()
@@ -402,14 +637,18 @@ impl BadActorModelController {
         pg_pool: &PgPool,
         id: i32,
         updated_by_user_id: UserId,
+        guild_id: GuildId,
         screenshot_path: impl Into<String>,
     ) -> anyhow::Result<BadActor> {
+        let mut tx = pg_pool.begin().await?;
+        let previous = Self::get_for_update(&mut tx, id).await?;
+
         let updated_db_bad_actor = sqlx::query_as::<_, DbBadActor>(
             r#"
             UPDATE bad_actors
             SET
                 screenshot_proof = $2,
-                last_updated_by = $3,
+                last_changed_by = $3,
                 updated_at = CURRENT_TIMESTAMP
             WHERE id = $1
             RETURNING *;
@@ -418,24 +657,48 @@ impl BadActorModelController {
         .bind(id)
         .bind(screenshot_path.into())
         .bind(updated_by_user_id.to_string())
-        .fetch_one(pg_pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        let event_type = if previous.screenshot_proof.is_some() {
+            BadActorEventType::ScreenshotReplaced
+        } else {
+            BadActorEventType::ScreenshotAdded
+        };
+
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: id,
+            event_type,
+            old_value: previous.screenshot_proof,
+            new_value: updated_db_bad_actor.screenshot_proof.clone(),
+            changed_by_user_id: updated_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
         updated_db_bad_actor.try_into()
     }
 
+    /// Update the explanation of a bad actor entry by its unique ID.
+    /// This also updates the `last_changed_by` field to the user ID of the user who updated the entry.
     pub async fn update_explanation(
         pg_pool: &PgPool,
         id: i32,
         updated_by_user_id: UserId,
+        guild_id: GuildId,
         explanation: impl Into<String>,
     ) -> anyhow::Result<BadActor> {
+        let mut tx = pg_pool.begin().await?;
+        let previous = Self::get_for_update(&mut tx, id).await?;
+
         let updated_db_bad_actor = sqlx::query_as::<_, DbBadActor>(
             r#"
             UPDATE bad_actors
             SET
                 explanation = $2,
-                last_updated_by = $3,
+                last_changed_by = $3,
                 updated_at = CURRENT_TIMESTAMP
             WHERE id = $1
             RETURNING *;
@@ -444,9 +707,30 @@ impl BadActorModelController {
         .bind(id)
         .bind(explanation.into())
         .bind(updated_by_user_id.to_string())
-        .fetch_one(pg_pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        let event = CreateBadActorEventOptions {
+            bad_actor_id: id,
+            event_type: BadActorEventType::ExplanationChanged,
+            old_value: previous.explanation,
+            new_value: updated_db_bad_actor.explanation.clone(),
+            changed_by_user_id: updated_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
         updated_db_bad_actor.try_into()
     }
+
+    /// Locks the row of a bad actor entry for the rest of the transaction and returns its current state.
+    async fn get_for_update(conn: &mut PgConnection, id: i32) -> anyhow::Result<DbBadActor> {
+        sqlx::query_as::<_, DbBadActor>("SELECT * FROM bad_actors WHERE id = $1 FOR UPDATE;")
+            .bind(id)
+            .fetch_one(conn)
+            .await
+            .map_err(anyhow::Error::from)
+    }
 }
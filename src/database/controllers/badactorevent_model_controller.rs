@@ -0,0 +1,192 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, UserId};
+use sqlx::{FromRow, PgConnection, PgPool};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BadActorEventType {
+    Created,
+    Deactivated,
+    Reactivated,
+    ScreenshotAdded,
+    ScreenshotReplaced,
+    EvidenceAdded,
+    EvidenceRemoved,
+    ExplanationChanged,
+    Deleted,
+}
+
+impl Display for BadActorEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created => write!(f, "created"),
+            Self::Deactivated => write!(f, "deactivated"),
+            Self::Reactivated => write!(f, "reactivated"),
+            Self::ScreenshotAdded => write!(f, "screenshot_added"),
+            Self::ScreenshotReplaced => write!(f, "screenshot_replaced"),
+            Self::EvidenceAdded => write!(f, "evidence_added"),
+            Self::EvidenceRemoved => write!(f, "evidence_removed"),
+            Self::ExplanationChanged => write!(f, "explanation_changed"),
+            Self::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+impl FromStr for BadActorEventType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(Self::Created),
+            "deactivated" => Ok(Self::Deactivated),
+            "reactivated" => Ok(Self::Reactivated),
+            "screenshot_added" => Ok(Self::ScreenshotAdded),
+            "screenshot_replaced" => Ok(Self::ScreenshotReplaced),
+            "evidence_added" => Ok(Self::EvidenceAdded),
+            "evidence_removed" => Ok(Self::EvidenceRemoved),
+            "explanation_changed" => Ok(Self::ExplanationChanged),
+            "deleted" => Ok(Self::Deleted),
+            _ => anyhow::bail!("Invalid bad actor event type: {}", s),
+        }
+    }
+}
+
+impl BadActorEventType {
+    /// Human readable name for displaying the event in Discord.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Created => "Created",
+            Self::Deactivated => "Deactivated",
+            Self::Reactivated => "Reactivated",
+            Self::ScreenshotAdded => "Screenshot Added",
+            Self::ScreenshotReplaced => "Screenshot Replaced",
+            Self::EvidenceAdded => "Evidence Added",
+            Self::EvidenceRemoved => "Evidence Removed",
+            Self::ExplanationChanged => "Explanation Changed",
+            Self::Deleted => "Deleted",
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct DbBadActorEvent {
+    id: i32,
+    event_type: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    changed_by: String,
+    guild_id: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct BadActorEvent {
+    pub id: i32,
+    pub event_type: BadActorEventType,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_by_user_id: UserId,
+    pub guild_id: GuildId,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbBadActorEvent> for BadActorEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(db_event: DbBadActorEvent) -> Result<Self, Self::Error> {
+        Ok(BadActorEvent {
+            id: db_event.id,
+            event_type: BadActorEventType::from_str(&db_event.event_type)?,
+            old_value: db_event.old_value,
+            new_value: db_event.new_value,
+            changed_by_user_id: UserId::from_str(&db_event.changed_by)?,
+            guild_id: GuildId::from_str(&db_event.guild_id)?,
+            created_at: db_event.created_at.and_utc(),
+        })
+    }
+}
+
+pub struct CreateBadActorEventOptions {
+    pub bad_actor_id: i32,
+    pub event_type: BadActorEventType,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_by_user_id: UserId,
+    pub guild_id: GuildId,
+}
+
+pub struct BadActorEventModelController;
+
+impl BadActorEventModelController {
+    /// Append an event to the history of a bad actor entry.
+    /// This takes a connection so it can be part of the transaction that changes the entry itself.
+    pub async fn create(
+        conn: &mut PgConnection,
+        options: CreateBadActorEventOptions,
+    ) -> anyhow::Result<()> {
+        let CreateBadActorEventOptions {
+            bad_actor_id,
+            event_type,
+            old_value,
+            new_value,
+            changed_by_user_id,
+            guild_id,
+        } = options;
+
+        sqlx::query(
+            r#"
+            INSERT INTO bad_actor_events (bad_actor_id, event_type, old_value, new_value, changed_by, guild_id)
+            VALUES ($1, $2, $3, $4, $5, $6);
+            "#,
+        )
+        .bind(bad_actor_id)
+        .bind(event_type.to_string())
+        .bind(old_value)
+        .bind(new_value)
+        .bind(changed_by_user_id.to_string())
+        .bind(guild_id.to_string())
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get a page of the history of a bad actor entry, oldest first.
+    pub async fn get_by_bad_actor_id(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<BadActorEvent>> {
+        sqlx::query_as::<_, DbBadActorEvent>(
+            r#"
+            SELECT * FROM bad_actor_events
+            WHERE bad_actor_id = $1
+            ORDER BY created_at ASC, id ASC
+            LIMIT $2 OFFSET $3;
+            "#,
+        )
+        .bind(bad_actor_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(BadActorEvent::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    /// Count the events recorded for a bad actor entry.
+    pub async fn count_by_bad_actor_id(db_pool: &PgPool, bad_actor_id: i32) -> anyhow::Result<i64> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM bad_actor_events WHERE bad_actor_id = $1;",
+        )
+        .bind(bad_actor_id)
+        .fetch_one(db_pool)
+        .await
+        .map_err(anyhow::Error::from)
+    }
+}
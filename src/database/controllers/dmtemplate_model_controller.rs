@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use sqlx::{FromRow, PgPool};
+
+use super::badactor_model_controller::BadActorType;
+
+const SPAM_TEMPLATE: &str = "It appears your account has been compromised and used as a spam bot.\n\nAs part of a collaborative effort to more efficiently moderate TMC servers, your account has been reported as a spam account (report {id}, reported in {origin}).\nSince not all guilds have automatic moderation, it's possible that you have been banned from more servers than you are aware of.\n\nIf you have now recovered your account, you can appeal your report by using `/appeal` in a direct message with this bot.\nOnce your appeal is accepted, your report will be deactivated.";
+
+const HONEYPOT_TEMPLATE: &str = "It appears your account has been compromised and used as a spam bot, because it posted in a channel that only bots write in.\n\nAs part of a collaborative effort to more efficiently moderate TMC servers, your account has been reported as a {type} account (report {id}, caught in {origin}).\nSince not all guilds have automatic moderation, it's possible that you have been banned from more servers than you are aware of.\n\nIf you have now recovered your account, you can appeal your report by using `/appeal` in a direct message with this bot.\nOnce your appeal is accepted, your report will be deactivated.";
+
+const IMPERSONATION_TEMPLATE: &str = "Your account has been reported for impersonating another user or staff member.\n\nAs part of a collaborative effort to more efficiently moderate TMC servers, the report (report {id}, reported in {origin}) has been shared with other servers, which might take moderation actions against your account.\n\nIf you think this report is a mistake, you can appeal it by using `/appeal` in a direct message with this bot.";
+
+const BIGOTRY_TEMPLATE: &str = "Your account has been reported for bigotry.\n\nAs part of a collaborative effort to more efficiently moderate TMC servers, the report (report {id}, reported in {origin}) has been shared with other servers, which might take moderation actions against your account.\n\nIf you think this report is a mistake, you can appeal it by using `/appeal` in a direct message with this bot.";
+
+/// The DM a reported user receives for a type of bad actor.
+#[derive(Debug, Clone)]
+pub struct DmTemplate {
+    pub actor_type: BadActorType,
+    /// A custom template set by an admin. `None` uses the built-in template.
+    pub content: Option<String>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct DbDmTemplate {
+    actor_type: String,
+    content: Option<String>,
+    enabled: bool,
+}
+
+impl TryFrom<DbDmTemplate> for DmTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(db_template: DbDmTemplate) -> Result<Self, Self::Error> {
+        Ok(DmTemplate {
+            actor_type: BadActorType::from_str(&db_template.actor_type)?,
+            content: db_template.content,
+            enabled: db_template.enabled,
+        })
+    }
+}
+
+impl DmTemplate {
+    fn built_in(actor_type: BadActorType) -> Self {
+        Self {
+            actor_type,
+            content: None,
+            enabled: true,
+        }
+    }
+
+    /// The template with its placeholders, custom or built-in.
+    pub fn template(&self) -> &str {
+        if let Some(content) = self.content.as_deref() {
+            return content;
+        }
+
+        match self.actor_type {
+            BadActorType::Spam => SPAM_TEMPLATE,
+            BadActorType::Impersonation => IMPERSONATION_TEMPLATE,
+            BadActorType::Bigotry => BIGOTRY_TEMPLATE,
+            BadActorType::Honeypot => HONEYPOT_TEMPLATE,
+        }
+    }
+
+    /// Fills in the `{id}`, `{type}` and `{origin}` placeholders.
+    pub fn render(&self, report_id: i32, origin: &str) -> String {
+        self.template()
+            .replace("{id}", report_id.to_string().as_str())
+            .replace("{type}", self.actor_type.to_string().as_str())
+            .replace("{origin}", origin)
+    }
+}
+
+pub struct DmTemplateModelController;
+
+impl DmTemplateModelController {
+    /// Get the template for a bad actor type, falling back to the built-in one.
+    pub async fn get(db_pool: &PgPool, actor_type: BadActorType) -> anyhow::Result<DmTemplate> {
+        let db_template =
+            sqlx::query_as::<_, DbDmTemplate>("SELECT * FROM dm_templates WHERE actor_type = $1;")
+                .bind(actor_type.to_string())
+                .fetch_optional(db_pool)
+                .await?;
+
+        match db_template {
+            Some(db_template) => db_template.try_into(),
+            None => Ok(DmTemplate::built_in(actor_type)),
+        }
+    }
+
+    /// Get the templates for all bad actor types.
+    pub async fn get_all(db_pool: &PgPool) -> anyhow::Result<Vec<DmTemplate>> {
+        let mut templates = Vec::with_capacity(BadActorType::ALL.len());
+
+        for actor_type in BadActorType::ALL {
+            templates.push(Self::get(db_pool, actor_type).await?);
+        }
+
+        Ok(templates)
+    }
+
+    /// Set a custom template for a bad actor type. `None` goes back to the built-in template.
+    pub async fn set_content(
+        db_pool: &PgPool,
+        actor_type: BadActorType,
+        content: Option<String>,
+    ) -> anyhow::Result<DmTemplate> {
+        sqlx::query_as::<_, DbDmTemplate>(
+            r#"
+            INSERT INTO dm_templates (actor_type, content)
+            VALUES ($1, $2)
+            ON CONFLICT (actor_type) DO UPDATE
+            SET content = $2, updated_at = CURRENT_TIMESTAMP
+            RETURNING *;
+            "#,
+        )
+        .bind(actor_type.to_string())
+        .bind(content)
+        .fetch_one(db_pool)
+        .await?
+        .try_into()
+    }
+
+    /// Enable or disable the DM for a bad actor type.
+    pub async fn set_enabled(
+        db_pool: &PgPool,
+        actor_type: BadActorType,
+        enabled: bool,
+    ) -> anyhow::Result<DmTemplate> {
+        sqlx::query_as::<_, DbDmTemplate>(
+            r#"
+            INSERT INTO dm_templates (actor_type, enabled)
+            VALUES ($1, $2)
+            ON CONFLICT (actor_type) DO UPDATE
+            SET enabled = $2, updated_at = CURRENT_TIMESTAMP
+            RETURNING *;
+            "#,
+        )
+        .bind(actor_type.to_string())
+        .bind(enabled)
+        .fetch_one(db_pool)
+        .await?
+        .try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders() {
+        let template = DmTemplate {
+            actor_type: BadActorType::Bigotry,
+            content: Some("Report {id} ({type}) from {origin}, again {id}.".to_string()),
+            enabled: true,
+        };
+
+        assert_eq!(
+            template.render(42, "Some Server"),
+            "Report 42 (bigotry) from Some Server, again 42."
+        );
+    }
+
+    #[test]
+    fn built_in_templates_match_actor_type() {
+        let impersonation = DmTemplate::built_in(BadActorType::Impersonation).render(1, "Origin");
+        assert!(impersonation.contains("impersonating"));
+        assert!(!impersonation.contains("spam bot"));
+
+        let spam = DmTemplate::built_in(BadActorType::Spam).render(1, "Origin");
+        assert!(spam.contains("spam bot"));
+        assert!(!spam.contains('{'));
+    }
+}
@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, UserId};
+use sqlx::{FromRow, PgPool};
+
+use crate::database::controllers::badactorevent_model_controller::{
+    BadActorEventModelController, BadActorEventType, CreateBadActorEventOptions,
+};
+
+#[derive(Debug, FromRow)]
+struct DbEvidence {
+    id: i32,
+    bad_actor_id: i32,
+    file_name: String,
+    position: i32,
+    added_by: String,
+    created_at: NaiveDateTime,
+}
+
+/// A file attached to a bad actor entry in addition to its screenshot proof.
+#[derive(Debug)]
+pub struct Evidence {
+    #[allow(dead_code)]
+    pub id: i32,
+    #[allow(dead_code)]
+    pub bad_actor_id: i32,
+    pub file_name: String,
+    /// 1-based position in which the evidence is displayed.
+    pub position: i32,
+    #[allow(dead_code)]
+    pub added_by_user_id: UserId,
+    #[allow(dead_code)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbEvidence> for Evidence {
+    type Error = anyhow::Error;
+
+    fn try_from(db_evidence: DbEvidence) -> Result<Self, Self::Error> {
+        Ok(Evidence {
+            id: db_evidence.id,
+            bad_actor_id: db_evidence.bad_actor_id,
+            file_name: db_evidence.file_name,
+            position: db_evidence.position,
+            added_by_user_id: UserId::from_str(&db_evidence.added_by)?,
+            created_at: db_evidence.created_at.and_utc(),
+        })
+    }
+}
+
+pub struct CreateEvidenceOptions {
+    pub bad_actor_id: i32,
+    pub file_name: String,
+    pub added_by_user_id: UserId,
+    pub guild_id: GuildId,
+}
+
+pub struct EvidenceModelController;
+
+impl EvidenceModelController {
+    /// Append a file to the evidence of a bad actor entry and record it in the history of the entry.
+    pub async fn create(
+        db_pool: &PgPool,
+        options: CreateEvidenceOptions,
+    ) -> anyhow::Result<Evidence> {
+        let CreateEvidenceOptions {
+            bad_actor_id,
+            file_name,
+            added_by_user_id,
+            guild_id,
+        } = options;
+
+        let mut tx = db_pool.begin().await?;
+
+        let db_evidence = sqlx::query_as::<_, DbEvidence>(
+            r#"
+            INSERT INTO evidence (bad_actor_id, file_name, position, added_by)
+            SELECT $1, $2, COALESCE(MAX(position), 0) + 1, $3
+            FROM evidence
+            WHERE bad_actor_id = $1
+            RETURNING *;
+            "#,
+        )
+        .bind(bad_actor_id)
+        .bind(&file_name)
+        .bind(added_by_user_id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let event = CreateBadActorEventOptions {
+            bad_actor_id,
+            event_type: BadActorEventType::EvidenceAdded,
+            old_value: None,
+            new_value: Some(file_name),
+            changed_by_user_id: added_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
+        db_evidence.try_into()
+    }
+
+    /// Get all evidence of a bad actor entry in the order it is displayed.
+    pub async fn get_by_bad_actor_id(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+    ) -> anyhow::Result<Vec<Evidence>> {
+        sqlx::query_as::<_, DbEvidence>(
+            "SELECT * FROM evidence WHERE bad_actor_id = $1 ORDER BY position ASC;",
+        )
+        .bind(bad_actor_id)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(Evidence::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    /// Count the evidence attached to a bad actor entry.
+    pub async fn count_by_bad_actor_id(db_pool: &PgPool, bad_actor_id: i32) -> anyhow::Result<i64> {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM evidence WHERE bad_actor_id = $1;")
+            .bind(bad_actor_id)
+            .fetch_one(db_pool)
+            .await
+            .map_err(anyhow::Error::from)
+    }
+
+    /// Remove the evidence at a position and move the evidence after it up by one.
+    /// Returns `None` if there is no evidence at that position.
+    pub async fn delete(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+        position: i32,
+        deleted_by_user_id: UserId,
+        guild_id: GuildId,
+    ) -> anyhow::Result<Option<Evidence>> {
+        let mut tx = db_pool.begin().await?;
+
+        let Some(db_evidence) = sqlx::query_as::<_, DbEvidence>(
+            "DELETE FROM evidence WHERE bad_actor_id = $1 AND position = $2 RETURNING *;",
+        )
+        .bind(bad_actor_id)
+        .bind(position)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "UPDATE evidence SET position = position - 1 WHERE bad_actor_id = $1 AND position > $2;",
+        )
+        .bind(bad_actor_id)
+        .bind(position)
+        .execute(&mut *tx)
+        .await?;
+
+        let event = CreateBadActorEventOptions {
+            bad_actor_id,
+            event_type: BadActorEventType::EvidenceRemoved,
+            old_value: Some(db_evidence.file_name.clone()),
+            new_value: None,
+            changed_by_user_id: deleted_by_user_id,
+            guild_id,
+        };
+        BadActorEventModelController::create(&mut tx, event).await?;
+
+        tx.commit().await?;
+
+        db_evidence.try_into().map(Some)
+    }
+}
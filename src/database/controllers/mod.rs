@@ -1,5 +1,13 @@
 pub mod admin_model_controller;
+pub mod appeal_model_controller;
 pub mod badactor_model_controller;
+pub mod badactorevent_model_controller;
+pub mod dmtemplate_model_controller;
+pub mod evidence_model_controller;
+pub mod moderationaction_model_controller;
+pub mod outbox_model_controller;
 pub mod scores_model_controller;
 pub mod serverconfig_model_controller;
+pub mod storedfile_model_controller;
 pub mod user_model_controller;
+pub mod webhook_model_controller;
@@ -0,0 +1,249 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, UserId};
+use sqlx::{FromRow, PgPool};
+
+use super::serverconfig_model_controller::ActionLevel;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModerationActionType {
+    Timeout,
+    Kick,
+    SoftBan,
+    Ban,
+    Unban,
+}
+
+impl Display for ModerationActionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timeout"),
+            Self::Kick => write!(f, "kick"),
+            Self::SoftBan => write!(f, "softban"),
+            Self::Ban => write!(f, "ban"),
+            Self::Unban => write!(f, "unban"),
+        }
+    }
+}
+
+impl FromStr for ModerationActionType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "timeout" => Ok(Self::Timeout),
+            "kick" => Ok(Self::Kick),
+            "softban" => Ok(Self::SoftBan),
+            "ban" => Ok(Self::Ban),
+            "unban" => Ok(Self::Unban),
+            _ => anyhow::bail!("Invalid moderation action type: {}", s),
+        }
+    }
+}
+
+impl ModerationActionType {
+    /// The action that is taken for an [ActionLevel]. [ActionLevel::Notify] does not take any action.
+    pub fn from_action_level(action_level: ActionLevel) -> Option<Self> {
+        match action_level {
+            ActionLevel::Notify => None,
+            ActionLevel::Timeout => Some(Self::Timeout),
+            ActionLevel::Kick => Some(Self::Kick),
+            ActionLevel::SoftBan => Some(Self::SoftBan),
+            ActionLevel::Ban => Some(Self::Ban),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModerationSource {
+    /// The action level a listener configured for the bad actor type.
+    Automatic,
+    /// A moderation button on a broadcast message.
+    Button,
+}
+
+impl Display for ModerationSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Automatic => write!(f, "automatic"),
+            Self::Button => write!(f, "button"),
+        }
+    }
+}
+
+impl FromStr for ModerationSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "automatic" => Ok(Self::Automatic),
+            "button" => Ok(Self::Button),
+            _ => anyhow::bail!("Invalid moderation source: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct DbModerationAction {
+    id: i32,
+    guild_id: String,
+    target_user_id: String,
+    bad_actor_id: Option<i32>,
+    action: String,
+    source: String,
+    acting_user_id: Option<String>,
+    success: bool,
+    error: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct ModerationAction {
+    pub id: i32,
+    pub guild_id: GuildId,
+    #[allow(dead_code)]
+    pub target_user_id: UserId,
+    #[allow(dead_code)]
+    pub bad_actor_id: Option<i32>,
+    pub action: ModerationActionType,
+    pub source: ModerationSource,
+    pub acting_user_id: Option<UserId>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbModerationAction> for ModerationAction {
+    type Error = anyhow::Error;
+
+    fn try_from(db_action: DbModerationAction) -> Result<Self, Self::Error> {
+        Ok(ModerationAction {
+            id: db_action.id,
+            guild_id: GuildId::from_str(&db_action.guild_id)?,
+            target_user_id: UserId::from_str(&db_action.target_user_id)?,
+            bad_actor_id: db_action.bad_actor_id,
+            action: ModerationActionType::from_str(&db_action.action)?,
+            source: ModerationSource::from_str(&db_action.source)?,
+            acting_user_id: db_action
+                .acting_user_id
+                .map(|id| UserId::from_str(&id))
+                .transpose()?,
+            success: db_action.success,
+            error: db_action.error,
+            created_at: db_action.created_at.and_utc(),
+        })
+    }
+}
+
+pub struct CreateModerationAction<'a> {
+    pub guild_id: GuildId,
+    pub target_user_id: UserId,
+    pub bad_actor_id: Option<i32>,
+    pub action: ModerationActionType,
+    pub source: ModerationSource,
+    pub acting_user_id: Option<UserId>,
+    pub result: &'a anyhow::Result<()>,
+}
+
+pub struct ModerationActionModelController;
+
+impl ModerationActionModelController {
+    /// Record a moderation action that was attempted, successful or not.
+    pub async fn create(
+        db_pool: &PgPool,
+        action: CreateModerationAction<'_>,
+    ) -> anyhow::Result<()> {
+        let CreateModerationAction {
+            guild_id,
+            target_user_id,
+            bad_actor_id,
+            action,
+            source,
+            acting_user_id,
+            result,
+        } = action;
+
+        let error = result.as_ref().err().map(|e| format!("{e:#}"));
+
+        sqlx::query(
+            r#"
+            INSERT INTO moderation_actions (guild_id, target_user_id, bad_actor_id, action, source, acting_user_id, success, error)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            "#,
+        )
+        .bind(guild_id.to_string())
+        .bind(target_user_id.to_string())
+        .bind(bad_actor_id)
+        .bind(action.to_string())
+        .bind(source.to_string())
+        .bind(acting_user_id.map(|id| id.to_string()))
+        .bind(result.is_ok())
+        .bind(error)
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether a ban Janitor applied for the bad actor entry is still in place in the guild,
+    /// meaning the latest successful ban or unban recorded there is a ban.
+    pub async fn has_janitor_ban(
+        db_pool: &PgPool,
+        guild_id: GuildId,
+        bad_actor_id: i32,
+    ) -> anyhow::Result<bool> {
+        let latest = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT action FROM moderation_actions
+            WHERE guild_id = $1 AND bad_actor_id = $2 AND success AND action IN ('ban', 'unban')
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1;
+            "#,
+        )
+        .bind(guild_id.to_string())
+        .bind(bad_actor_id)
+        .fetch_optional(db_pool)
+        .await?;
+
+        Ok(latest == Some(ModerationActionType::Ban.to_string()))
+    }
+
+    /// Get a page of the moderation actions taken for a bad actor entry, oldest first.
+    pub async fn get_by_bad_actor_id(
+        db_pool: &PgPool,
+        bad_actor_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<ModerationAction>> {
+        sqlx::query_as::<_, DbModerationAction>(
+            r#"
+            SELECT * FROM moderation_actions
+            WHERE bad_actor_id = $1
+            ORDER BY created_at ASC, id ASC
+            LIMIT $2 OFFSET $3;
+            "#,
+        )
+        .bind(bad_actor_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(ModerationAction::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    /// Count the moderation actions recorded for a bad actor entry.
+    pub async fn count_by_bad_actor_id(db_pool: &PgPool, bad_actor_id: i32) -> anyhow::Result<i64> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM moderation_actions WHERE bad_actor_id = $1;",
+        )
+        .bind(bad_actor_id)
+        .fetch_one(db_pool)
+        .await
+        .map_err(anyhow::Error::from)
+    }
+}
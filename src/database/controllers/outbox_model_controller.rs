@@ -0,0 +1,272 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, UserId};
+use sqlx::{FromRow, PgPool};
+
+use crate::broadcast::broadcast_handler::BroadcastType;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeliveryType {
+    /// The broadcast message in the log channel of a listener.
+    Listener,
+    /// The automatic moderation action of a listener.
+    Moderation,
+    /// The broadcast message to a webhook.
+    Webhook,
+}
+
+impl Display for DeliveryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Listener => write!(f, "listener"),
+            Self::Moderation => write!(f, "moderation"),
+            Self::Webhook => write!(f, "webhook"),
+        }
+    }
+}
+
+impl FromStr for DeliveryType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "listener" => Ok(Self::Listener),
+            "moderation" => Ok(Self::Moderation),
+            "webhook" => Ok(Self::Webhook),
+            _ => anyhow::bail!("Invalid delivery type: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    /// The delivery ran out of attempts and will only be tried again when it is replayed.
+    Failed,
+}
+
+impl Display for DeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "failed" => Ok(Self::Failed),
+            _ => anyhow::bail!("Invalid delivery status: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct DbOutboxDelivery {
+    id: i32,
+    delivery_type: String,
+    target_guild_id: String,
+    bad_actor_id: i32,
+    broadcast_type: String,
+    reporting_user_id: String,
+    origin_guild_id: String,
+    attempts: i32,
+    last_error: Option<String>,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct OutboxDelivery {
+    pub id: i32,
+    pub delivery_type: DeliveryType,
+    pub target_guild_id: GuildId,
+    pub bad_actor_id: i32,
+    pub broadcast_type: BroadcastType,
+    pub reporting_user_id: UserId,
+    pub origin_guild_id: GuildId,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbOutboxDelivery> for OutboxDelivery {
+    type Error = anyhow::Error;
+
+    fn try_from(db_delivery: DbOutboxDelivery) -> Result<Self, Self::Error> {
+        Ok(OutboxDelivery {
+            id: db_delivery.id,
+            delivery_type: DeliveryType::from_str(&db_delivery.delivery_type)?,
+            target_guild_id: GuildId::from_str(&db_delivery.target_guild_id)?,
+            bad_actor_id: db_delivery.bad_actor_id,
+            broadcast_type: BroadcastType::from_str(&db_delivery.broadcast_type)?,
+            reporting_user_id: UserId::from_str(&db_delivery.reporting_user_id)?,
+            origin_guild_id: GuildId::from_str(&db_delivery.origin_guild_id)?,
+            attempts: db_delivery.attempts,
+            last_error: db_delivery.last_error,
+            updated_at: db_delivery.updated_at.and_utc(),
+        })
+    }
+}
+
+pub struct CreateOutboxDelivery {
+    pub delivery_type: DeliveryType,
+    pub target_guild_id: GuildId,
+    pub bad_actor_id: i32,
+    pub broadcast_type: BroadcastType,
+    pub reporting_user_id: UserId,
+    pub origin_guild_id: GuildId,
+}
+
+pub struct OutboxModelController;
+
+impl OutboxModelController {
+    /// Add deliveries to the outbox. They are leased for `lease_secs` so the worker leaves them alone while they are attempted right away.
+    pub async fn create_many(
+        db_pool: &PgPool,
+        deliveries: Vec<CreateOutboxDelivery>,
+        lease_secs: f64,
+    ) -> anyhow::Result<Vec<OutboxDelivery>> {
+        let mut tx = db_pool.begin().await?;
+        let mut created = Vec::with_capacity(deliveries.len());
+
+        for delivery in deliveries {
+            let db_delivery = sqlx::query_as::<_, DbOutboxDelivery>(
+                r#"
+                INSERT INTO broadcast_outbox (delivery_type, target_guild_id, bad_actor_id, broadcast_type, reporting_user_id, origin_guild_id, next_attempt_at)
+                VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP + make_interval(secs => $7))
+                RETURNING *;
+                "#,
+            )
+            .bind(delivery.delivery_type.to_string())
+            .bind(delivery.target_guild_id.to_string())
+            .bind(delivery.bad_actor_id)
+            .bind(delivery.broadcast_type.to_string())
+            .bind(delivery.reporting_user_id.to_string())
+            .bind(delivery.origin_guild_id.to_string())
+            .bind(lease_secs)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            created.push(db_delivery.try_into()?);
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    /// Get pending deliveries that are due and lease them for `lease_secs` so they are not picked up twice.
+    pub async fn claim_due(
+        db_pool: &PgPool,
+        limit: i64,
+        lease_secs: f64,
+    ) -> anyhow::Result<Vec<OutboxDelivery>> {
+        sqlx::query_as::<_, DbOutboxDelivery>(
+            r#"
+            UPDATE broadcast_outbox
+            SET next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM broadcast_outbox
+                WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+                ORDER BY next_attempt_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *;
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(OutboxDelivery::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    /// Remove a delivery from the outbox after it succeeded.
+    pub async fn delete(db_pool: &PgPool, id: i32) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM broadcast_outbox WHERE id = $1;")
+            .bind(id)
+            .execute(db_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt. The delivery is either retried after `retry_in_secs` or marked as failed if `retry_in_secs` is `None`.
+    pub async fn record_failure(
+        db_pool: &PgPool,
+        id: i32,
+        error: impl Into<String>,
+        retry_in_secs: Option<f64>,
+    ) -> anyhow::Result<()> {
+        let status = match retry_in_secs {
+            Some(_) => DeliveryStatus::Pending,
+            None => DeliveryStatus::Failed,
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE broadcast_outbox
+            SET
+                attempts = attempts + 1,
+                status = $2,
+                last_error = $3,
+                next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $4),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1;
+            "#,
+        )
+        .bind(id)
+        .bind(status.to_string())
+        .bind(error.into())
+        .bind(retry_in_secs.unwrap_or(0.0))
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the most recently failed deliveries.
+    pub async fn get_failed(db_pool: &PgPool, limit: i64) -> anyhow::Result<Vec<OutboxDelivery>> {
+        sqlx::query_as::<_, DbOutboxDelivery>(
+            "SELECT * FROM broadcast_outbox WHERE status = 'failed' ORDER BY updated_at DESC LIMIT $1;",
+        )
+        .bind(limit)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(OutboxDelivery::try_from)
+        .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    /// Reset failed deliveries so the worker picks them up again. Replays all failed deliveries if no ID is given.
+    /// Returns the amount of deliveries that will be replayed.
+    pub async fn replay(db_pool: &PgPool, id: Option<i32>) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE broadcast_outbox
+            SET
+                status = 'pending',
+                attempts = 0,
+                next_attempt_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE status = 'failed' AND ($1::int IS NULL OR id = $1);
+            "#,
+        )
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -9,8 +9,12 @@ use serenity::{
 };
 use sqlx::{prelude::FromRow, PgPool};
 
+use crate::broadcast::broadcast_handler::BroadcastType;
+use crate::broadcast::filter::BroadcastFilter;
+use crate::database::controllers::badactor_model_controller::BadActorType;
 use crate::database::controllers::user_model_controller::UserModelController;
 use crate::honeypot::channels::{populate_honeypot_channels, HoneypotChannels};
+use crate::honeypot::heuristics::{ContentSimilarity, HoneypotHeuristics};
 use crate::util::{embeds, format};
 
 #[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
@@ -68,6 +72,19 @@ struct DbServerConfig {
     honeypot_action_level: i32,
     ban_reason: Option<String>,
     honeypot_timeout: i32,
+    honeypot_window: i32,
+    honeypot_min_channels: i32,
+    honeypot_single_hit: bool,
+    honeypot_similarity: i32,
+    cross_server_detection: bool,
+    log_actor_types: Vec<String>,
+    log_broadcast_types: Vec<String>,
+    auto_unban_on_deactivate: bool,
+    spam_action_timeout: i32,
+    impersonation_action_timeout: i32,
+    bigotry_action_timeout: i32,
+    honeypot_action_timeout: i32,
+    ban_delete_message_days: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -86,6 +103,44 @@ pub struct ServerConfig {
     pub updated_at: DateTime<Utc>,
     pub ban_reason: Option<String>,
     pub honeypot_timeout: Duration,
+    pub honeypot_window: Duration,
+    pub honeypot_min_channels: u8,
+    pub honeypot_single_hit: bool,
+    pub honeypot_similarity: ContentSimilarity,
+    pub cross_server_detection: bool,
+    /// Which broadcasts are posted in the log channel. Automatic moderation is not affected by this.
+    pub log_filter: BroadcastFilter,
+    /// Lift bans Janitor applied when the report is deactivated.
+    pub auto_unban_on_deactivate: bool,
+    /// How long [ActionLevel::Timeout] times out users of each bad actor type.
+    pub spam_action_timeout: Duration,
+    pub impersonation_action_timeout: Duration,
+    pub bigotry_action_timeout: Duration,
+    pub honeypot_action_timeout: Duration,
+    /// Days of messages deleted when banning or softbanning, between 0 and 7.
+    pub ban_delete_message_days: u8,
+}
+
+impl ServerConfig {
+    /// How long [ActionLevel::Timeout] times out users reported as the given type.
+    pub fn action_timeout(&self, actor_type: BadActorType) -> Duration {
+        match actor_type {
+            BadActorType::Spam => self.spam_action_timeout,
+            BadActorType::Impersonation => self.impersonation_action_timeout,
+            BadActorType::Bigotry => self.bigotry_action_timeout,
+            BadActorType::Honeypot => self.honeypot_action_timeout,
+        }
+    }
+
+    pub fn honeypot_heuristics(&self) -> HoneypotHeuristics {
+        HoneypotHeuristics {
+            window: self.honeypot_window.to_std().unwrap_or_default(),
+            min_channels: self.honeypot_min_channels as usize,
+            single_hit: self.honeypot_single_hit,
+            similarity: self.honeypot_similarity,
+            cross_server: self.cross_server_detection,
+        }
+    }
 }
 
 impl TryFrom<DbServerConfig> for ServerConfig {
@@ -107,6 +162,19 @@ impl TryFrom<DbServerConfig> for ServerConfig {
             updated_at,
             ban_reason,
             honeypot_timeout,
+            honeypot_window,
+            honeypot_min_channels,
+            honeypot_single_hit,
+            honeypot_similarity,
+            cross_server_detection,
+            log_actor_types,
+            log_broadcast_types,
+            auto_unban_on_deactivate,
+            spam_action_timeout,
+            impersonation_action_timeout,
+            bigotry_action_timeout,
+            honeypot_action_timeout,
+            ban_delete_message_days,
         } = db_server_config;
 
         let guild_id = GuildId::from_str(&server_id)?;
@@ -129,6 +197,16 @@ impl TryFrom<DbServerConfig> for ServerConfig {
         let updated_at = updated_at.and_utc();
 
         let honeypot_timeout = Duration::minutes(honeypot_timeout as i64);
+        let honeypot_window = Duration::seconds(honeypot_window as i64);
+        let honeypot_min_channels = u8::try_from(honeypot_min_channels)?;
+        let honeypot_similarity = ContentSimilarity::try_from(honeypot_similarity)?;
+        let log_filter = BroadcastFilter::try_from_db(&log_actor_types, &log_broadcast_types)?;
+
+        let spam_action_timeout = Duration::minutes(spam_action_timeout as i64);
+        let impersonation_action_timeout = Duration::minutes(impersonation_action_timeout as i64);
+        let bigotry_action_timeout = Duration::minutes(bigotry_action_timeout as i64);
+        let honeypot_action_timeout = Duration::minutes(honeypot_action_timeout as i64);
+        let ban_delete_message_days = u8::try_from(ban_delete_message_days)?;
 
         Ok(ServerConfig {
             guild_id,
@@ -145,6 +223,18 @@ impl TryFrom<DbServerConfig> for ServerConfig {
             updated_at,
             ban_reason,
             honeypot_timeout,
+            honeypot_window,
+            honeypot_min_channels,
+            honeypot_single_hit,
+            honeypot_similarity,
+            cross_server_detection,
+            log_filter,
+            auto_unban_on_deactivate,
+            spam_action_timeout,
+            impersonation_action_timeout,
+            bigotry_action_timeout,
+            honeypot_action_timeout,
+            ban_delete_message_days,
         })
     }
 }
@@ -245,6 +335,22 @@ impl ServerConfigComplete {
             )
         };
 
+        let action_timeouts = format!(
+            "Spam: {} Minutes\nImpersonation: {} Minutes\nBigotry: {} Minutes\nHoneypot: {} Minutes",
+            self.server_config.spam_action_timeout.num_minutes(),
+            self.server_config.impersonation_action_timeout.num_minutes(),
+            self.server_config.bigotry_action_timeout.num_minutes(),
+            self.server_config.honeypot_action_timeout.num_minutes()
+        );
+
+        let honeypot_detection = format!(
+            "Window: {} Seconds\nMinimum Channels: {}\nSingle Hit: {}\nSimilarity: {}",
+            self.server_config.honeypot_window.num_seconds(),
+            self.server_config.honeypot_min_channels,
+            format::display_bool(self.server_config.honeypot_single_hit),
+            self.server_config.honeypot_similarity
+        );
+
         embeds::CreateJanitorEmbed::new(interaction_user)
             .into_embed()
             .title(format!("Server Config for {}", &self.guild.name))
@@ -257,9 +363,31 @@ impl ServerConfigComplete {
             .field("Impersonation Action Level", impersonation, false)
             .field("Bigotry Action Level", bigotry, false)
             .field("Honeypot Action Level", honeypot, false)
+            .field("Timeout Action Durations", action_timeouts, false)
             .field("Ignored Roles", ignored_roles, false)
             .field("Custom Ban Reason", ban_reason, false)
+            .field(
+                "Delete Messages on Ban",
+                format!("{} Days", self.server_config.ban_delete_message_days),
+                false,
+            )
             .field("Honeypot Timeout", honeypot_timeout, false)
+            .field("Honeypot Detection", honeypot_detection, false)
+            .field(
+                "Cross-Server Detection",
+                format::display_bool(self.server_config.cross_server_detection),
+                false,
+            )
+            .field(
+                "Automatic Unban on Deactivation",
+                format::display_bool(self.server_config.auto_unban_on_deactivate),
+                false,
+            )
+            .field(
+                "Log Channel Filter",
+                self.server_config.log_filter.to_string(),
+                false,
+            )
             .field("Created At", created_at, false)
             .field("Updated At", updated_at, false)
     }
@@ -276,6 +404,19 @@ pub struct UpdateServerConfig {
     pub ignored_roles: Option<Vec<RoleId>>,
     pub ban_reason: Option<String>,
     pub honeypot_timeout_minutes: i32,
+    pub honeypot_window_seconds: Option<i32>,
+    pub honeypot_min_channels: Option<i32>,
+    pub honeypot_single_hit: Option<bool>,
+    pub honeypot_similarity: Option<ContentSimilarity>,
+    pub cross_server_detection: Option<bool>,
+    pub log_actor_types: Option<Vec<BadActorType>>,
+    pub log_broadcast_types: Option<Vec<BroadcastType>>,
+    pub auto_unban_on_deactivate: Option<bool>,
+    pub spam_action_timeout_minutes: Option<i32>,
+    pub impersonation_action_timeout_minutes: Option<i32>,
+    pub bigotry_action_timeout_minutes: Option<i32>,
+    pub honeypot_action_timeout_minutes: Option<i32>,
+    pub ban_delete_message_days: Option<i32>,
 }
 
 pub struct ServerConfigModelController;
@@ -413,6 +554,63 @@ impl ServerConfigModelController {
 
         let honepot_timeout = update.honeypot_timeout_minutes;
 
+        let honeypot_window = update
+            .honeypot_window_seconds
+            .unwrap_or(previous.honeypot_window);
+
+        let honeypot_min_channels = update
+            .honeypot_min_channels
+            .unwrap_or(previous.honeypot_min_channels);
+
+        let honeypot_single_hit = update
+            .honeypot_single_hit
+            .unwrap_or(previous.honeypot_single_hit);
+
+        let honeypot_similarity = update
+            .honeypot_similarity
+            .map(|similarity| similarity as i32)
+            .unwrap_or(previous.honeypot_similarity);
+
+        let cross_server_detection = update
+            .cross_server_detection
+            .unwrap_or(previous.cross_server_detection);
+
+        let auto_unban_on_deactivate = update
+            .auto_unban_on_deactivate
+            .unwrap_or(previous.auto_unban_on_deactivate);
+
+        let spam_action_timeout = update
+            .spam_action_timeout_minutes
+            .unwrap_or(previous.spam_action_timeout);
+
+        let impersonation_action_timeout = update
+            .impersonation_action_timeout_minutes
+            .unwrap_or(previous.impersonation_action_timeout);
+
+        let bigotry_action_timeout = update
+            .bigotry_action_timeout_minutes
+            .unwrap_or(previous.bigotry_action_timeout);
+
+        let honeypot_action_timeout = update
+            .honeypot_action_timeout_minutes
+            .unwrap_or(previous.honeypot_action_timeout);
+
+        let ban_delete_message_days = update
+            .ban_delete_message_days
+            .unwrap_or(previous.ban_delete_message_days);
+
+        let previous_log_filter =
+            BroadcastFilter::try_from_db(&previous.log_actor_types, &previous.log_broadcast_types)?;
+
+        let log_filter = BroadcastFilter {
+            actor_types: update
+                .log_actor_types
+                .unwrap_or(previous_log_filter.actor_types),
+            broadcast_types: update
+                .log_broadcast_types
+                .unwrap_or(previous_log_filter.broadcast_types),
+        };
+
         let db_config = sqlx::query_as::<_, DbServerConfig>(
             r#"
             UPDATE server_configs
@@ -426,6 +624,19 @@ impl ServerConfigModelController {
                 ignored_roles = $9,
                 ban_reason = $10,
                 honeypot_timeout = $11,
+                honeypot_window = $12,
+                honeypot_min_channels = $13,
+                honeypot_single_hit = $14,
+                honeypot_similarity = $15,
+                cross_server_detection = $16,
+                log_actor_types = $17,
+                log_broadcast_types = $18,
+                auto_unban_on_deactivate = $19,
+                spam_action_timeout = $20,
+                impersonation_action_timeout = $21,
+                bigotry_action_timeout = $22,
+                honeypot_action_timeout = $23,
+                ban_delete_message_days = $24,
                 updated_at = now()
             WHERE server_id = $1
             RETURNING *;
@@ -442,6 +653,19 @@ impl ServerConfigModelController {
         .bind(&ignored_roles)
         .bind(ban_reason)
         .bind(honepot_timeout)
+        .bind(honeypot_window)
+        .bind(honeypot_min_channels)
+        .bind(honeypot_single_hit)
+        .bind(honeypot_similarity)
+        .bind(cross_server_detection)
+        .bind(log_filter.actor_types_to_db())
+        .bind(log_filter.broadcast_types_to_db())
+        .bind(auto_unban_on_deactivate)
+        .bind(spam_action_timeout)
+        .bind(impersonation_action_timeout)
+        .bind(bigotry_action_timeout)
+        .bind(honeypot_action_timeout)
+        .bind(ban_delete_message_days)
         .fetch_one(pg_pool)
         .await?;
 
@@ -472,7 +696,7 @@ impl ServerConfigModelController {
 
         tracing::info!("Deleted unused server config for guild {guild_id}");
 
-        populate_honeypot_channels(honeypot_channels, pg_pool).await;
+        populate_honeypot_channels(honeypot_channels, pg_pool).await?;
         tracing::info!("Repopulated honeypot channels");
 
         Ok(deleted)
@@ -490,7 +714,7 @@ impl ServerConfigModelController {
             .execute(pg_pool)
             .await?;
 
-        populate_honeypot_channels(honeypot_channels, pg_pool).await;
+        populate_honeypot_channels(honeypot_channels, pg_pool).await?;
         tracing::info!("Repopulated honeypot channels");
 
         Ok(())
@@ -506,7 +730,7 @@ impl ServerConfigModelController {
             .execute(pg_pool)
             .await?;
 
-        populate_honeypot_channels(honeypot_channels, pg_pool).await;
+        populate_honeypot_channels(honeypot_channels, pg_pool).await?;
         tracing::info!("Repopulated honeypot channels");
 
         Ok(())
@@ -0,0 +1,81 @@
+use sqlx::{FromRow, PgPool};
+
+/// The recorded hash and type of a screenshot or evidence file in the storage.
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct StoredFile {
+    pub file_name: String,
+    /// Hex encoded SHA-256 of the content.
+    pub sha256: String,
+    pub mime_type: String,
+    pub size: i32,
+}
+
+pub struct StoredFileModelController;
+
+impl StoredFileModelController {
+    /// Record a stored file. Uploading the same content again keeps the existing record.
+    pub async fn create(db_pool: &PgPool, stored_file: &StoredFile) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO stored_files (file_name, sha256, mime_type, size)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (file_name) DO NOTHING;
+            "#,
+        )
+        .bind(&stored_file.file_name)
+        .bind(&stored_file.sha256)
+        .bind(&stored_file.mime_type)
+        .bind(stored_file.size)
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_all(db_pool: &PgPool) -> anyhow::Result<Vec<StoredFile>> {
+        sqlx::query_as::<_, StoredFile>(
+            "SELECT file_name, sha256, mime_type, size FROM stored_files ORDER BY created_at ASC;",
+        )
+        .fetch_all(db_pool)
+        .await
+        .map_err(anyhow::Error::from)
+    }
+
+    /// Whether a report still uses the file as its screenshot proof or evidence.
+    pub async fn is_referenced(db_pool: &PgPool, file_name: &str) -> anyhow::Result<bool> {
+        sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT
+                EXISTS (SELECT 1 FROM bad_actors WHERE screenshot_proof = $1)
+                OR EXISTS (SELECT 1 FROM evidence WHERE file_name = $1);
+            "#,
+        )
+        .bind(file_name)
+        .fetch_one(db_pool)
+        .await
+        .map_err(anyhow::Error::from)
+    }
+
+    /// The names of all files used as screenshot proof or evidence.
+    pub async fn get_referenced_file_names(db_pool: &PgPool) -> anyhow::Result<Vec<String>> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT screenshot_proof FROM bad_actors WHERE screenshot_proof IS NOT NULL
+            UNION
+            SELECT file_name FROM evidence;
+            "#,
+        )
+        .fetch_all(db_pool)
+        .await
+        .map_err(anyhow::Error::from)
+    }
+
+    pub async fn delete(db_pool: &PgPool, file_name: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM stored_files WHERE file_name = $1;")
+            .bind(file_name)
+            .execute(db_pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use poise::serenity_prelude as serenity;
+use serenity::GuildId;
+use sqlx::{FromRow, PgPool};
+use url::Url;
+
+use crate::broadcast::filter::BroadcastFilter;
+
+#[derive(Debug, FromRow)]
+struct DbBroadcastWebhook {
+    guild_id: String,
+    guild_name: String,
+    webhook_url: String,
+    actor_types: Vec<String>,
+    broadcast_types: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BroadcastWebhook {
+    pub guild_id: GuildId,
+    pub guild_name: String,
+    pub webhook_url: Url,
+    pub filter: BroadcastFilter,
+}
+
+impl TryFrom<DbBroadcastWebhook> for BroadcastWebhook {
+    type Error = anyhow::Error;
+
+    fn try_from(db_webhook: DbBroadcastWebhook) -> Result<Self, Self::Error> {
+        let guild_id = GuildId::from_str(&db_webhook.guild_id)?;
+        let webhook_url = Url::from_str(&db_webhook.webhook_url)?;
+        let filter =
+            BroadcastFilter::try_from_db(&db_webhook.actor_types, &db_webhook.broadcast_types)?;
+
+        Ok(Self {
+            guild_id,
+            webhook_url,
+            guild_name: db_webhook.guild_name,
+            filter,
+        })
+    }
+}
+
+pub struct WebhookModelController;
+
+impl WebhookModelController {
+    /// Add the webhook of a guild or replace it if the guild already has one.
+    /// The filter of an existing webhook is kept.
+    pub async fn upsert(
+        db_pool: &PgPool,
+        webhook: &BroadcastWebhook,
+    ) -> anyhow::Result<BroadcastWebhook> {
+        sqlx::query_as::<_, DbBroadcastWebhook>(
+            r#"
+            INSERT INTO webhooks (guild_id, guild_name, webhook_url)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id) DO UPDATE
+            SET guild_name = EXCLUDED.guild_name, webhook_url = EXCLUDED.webhook_url
+            RETURNING *;
+            "#,
+        )
+        .bind(webhook.guild_id.to_string())
+        .bind(&webhook.guild_name)
+        .bind(webhook.webhook_url.as_str())
+        .fetch_one(db_pool)
+        .await?
+        .try_into()
+    }
+
+    /// Set which broadcasts the webhook of a guild receives. Returns `None` if the guild doesn't have a webhook.
+    pub async fn update_filter(
+        db_pool: &PgPool,
+        guild_id: GuildId,
+        filter: &BroadcastFilter,
+    ) -> anyhow::Result<Option<BroadcastWebhook>> {
+        sqlx::query_as::<_, DbBroadcastWebhook>(
+            "UPDATE webhooks SET actor_types = $2, broadcast_types = $3 WHERE guild_id = $1 RETURNING *;",
+        )
+        .bind(guild_id.to_string())
+        .bind(filter.actor_types_to_db())
+        .bind(filter.broadcast_types_to_db())
+        .fetch_optional(db_pool)
+        .await?
+        .map(BroadcastWebhook::try_from)
+        .transpose()
+    }
+
+    /// Remove the webhook of a guild. Returns `None` if the guild didn't have one.
+    pub async fn delete(
+        db_pool: &PgPool,
+        guild_id: GuildId,
+    ) -> anyhow::Result<Option<BroadcastWebhook>> {
+        sqlx::query_as::<_, DbBroadcastWebhook>(
+            "DELETE FROM webhooks WHERE guild_id = $1 RETURNING *;",
+        )
+        .bind(guild_id.to_string())
+        .fetch_optional(db_pool)
+        .await?
+        .map(BroadcastWebhook::try_from)
+        .transpose()
+    }
+
+    pub async fn get_all(db_pool: &PgPool) -> anyhow::Result<Vec<BroadcastWebhook>> {
+        sqlx::query_as::<_, DbBroadcastWebhook>("SELECT * FROM webhooks ORDER BY guild_name;")
+            .fetch_all(db_pool)
+            .await?
+            .into_iter()
+            .map(BroadcastWebhook::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()
+    }
+
+    pub async fn get_by_guild_id(
+        db_pool: &PgPool,
+        guild_id: GuildId,
+    ) -> anyhow::Result<Option<BroadcastWebhook>> {
+        sqlx::query_as::<_, DbBroadcastWebhook>("SELECT * FROM webhooks WHERE guild_id = $1;")
+            .bind(guild_id.to_string())
+            .fetch_optional(db_pool)
+            .await?
+            .map(BroadcastWebhook::try_from)
+            .transpose()
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::PgPool;
+
+/// All migrations in `src/database/migrations`, embedded into the binary at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!("src/database/migrations");
+
+/// Applies all pending migrations to the database and records them in the `_sqlx_migrations` table.
+/// Refuses to continue if the database has migrations applied that this binary does not know about.
+pub async fn run(db_pool: &PgPool) -> anyhow::Result<()> {
+    let mut conn = db_pool.acquire().await?;
+
+    conn.ensure_migrations_table()
+        .await
+        .context("Failed to create the migrations table")?;
+
+    let applied = conn
+        .list_applied_migrations()
+        .await
+        .context("Failed to get the applied migrations from the database")?
+        .into_iter()
+        .map(|m| m.version)
+        .collect::<HashSet<_>>();
+
+    drop(conn);
+
+    let known = MIGRATOR.iter().map(|m| m.version).collect::<HashSet<_>>();
+
+    if let Some(unknown) = applied.iter().filter(|v| !known.contains(v)).max() {
+        anyhow::bail!(
+            "The database has migration {unknown} applied which this binary does not know about. Refusing to start with a schema that is ahead of the binary."
+        );
+    }
+
+    for migration in MIGRATOR.iter().filter(|m| !applied.contains(&m.version)) {
+        tracing::info!(
+            "Applying migration {} ({})",
+            migration.version,
+            migration.description
+        );
+    }
+
+    MIGRATOR
+        .run(db_pool)
+        .await
+        .context("Failed to apply database migrations")?;
+
+    Ok(())
+}
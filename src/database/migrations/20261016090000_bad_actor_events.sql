@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS bad_actor_events (
+    id SERIAL PRIMARY KEY,
+    bad_actor_id INT NOT NULL, -- no foreign key, the history outlives deleted entries
+    event_type VARCHAR(25) NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    changed_by VARCHAR(20) NOT NULL,
+    guild_id VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS bad_actor_events_bad_actor_id_idx ON bad_actor_events (bad_actor_id);
@@ -0,0 +1,5 @@
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS honeypot_window INT NOT NULL DEFAULT 60, -- seconds
+    ADD COLUMN IF NOT EXISTS honeypot_min_channels INT NOT NULL DEFAULT 3,
+    ADD COLUMN IF NOT EXISTS honeypot_single_hit BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS honeypot_similarity INT NOT NULL DEFAULT 0; -- 'exact', 'normalized', 'url only' or 'fuzzy'
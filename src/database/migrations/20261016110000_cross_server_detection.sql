@@ -0,0 +1,2 @@
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS cross_server_detection BOOLEAN NOT NULL DEFAULT FALSE;
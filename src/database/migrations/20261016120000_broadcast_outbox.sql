@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS broadcast_outbox (
+    id SERIAL PRIMARY KEY,
+    delivery_type VARCHAR(15) NOT NULL, -- 'listener', 'moderation' or 'webhook'
+    target_guild_id VARCHAR(20) NOT NULL,
+    bad_actor_id INT NOT NULL,
+    broadcast_type VARCHAR(25) NOT NULL,
+    reporting_user_id VARCHAR(20) NOT NULL,
+    origin_guild_id VARCHAR(20) NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending', -- 'pending' or 'failed', delivered rows are deleted
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS broadcast_outbox_status_next_attempt_at_idx ON broadcast_outbox (status, next_attempt_at);
@@ -0,0 +1,8 @@
+-- empty arrays mean that every type is received
+ALTER TABLE webhooks
+    ADD COLUMN IF NOT EXISTS actor_types VARCHAR(15)[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS broadcast_types VARCHAR(25)[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS log_actor_types VARCHAR(15)[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS log_broadcast_types VARCHAR(25)[] NOT NULL DEFAULT '{}';
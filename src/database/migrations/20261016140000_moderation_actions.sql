@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS moderation_actions (
+    id SERIAL PRIMARY KEY,
+    guild_id VARCHAR(20) NOT NULL,
+    target_user_id VARCHAR(20) NOT NULL,
+    bad_actor_id INT, -- no foreign key, the log outlives deleted entries
+    action VARCHAR(15) NOT NULL, -- 'timeout', 'kick', 'softban', 'ban' or 'unban'
+    source VARCHAR(15) NOT NULL, -- 'automatic' or 'button'
+    acting_user_id VARCHAR(20), -- NULL for automatic actions
+    success BOOLEAN NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS moderation_actions_bad_actor_id_idx ON moderation_actions (bad_actor_id);
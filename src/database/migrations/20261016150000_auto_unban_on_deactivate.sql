@@ -0,0 +1,2 @@
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS auto_unban_on_deactivate BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS appeals (
+    id SERIAL PRIMARY KEY,
+    bad_actor_id INT NOT NULL REFERENCES bad_actors(id) ON DELETE CASCADE,
+    user_id VARCHAR(20) NOT NULL,
+    content TEXT NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'open', -- 'open', 'accepted' or 'rejected'
+    resolved_by_user_id VARCHAR(20),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS appeals_bad_actor_id_idx ON appeals (bad_actor_id);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS dm_templates (
+    actor_type VARCHAR(15) PRIMARY KEY, -- 'spam', 'impersonation', 'bigotry' or 'honeypot'
+    content TEXT, -- NULL uses the built-in template for the type
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+ALTER TABLE bad_actors ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP; -- NULL for reports that never expire
+
+CREATE INDEX IF NOT EXISTS bad_actors_expires_at_idx ON bad_actors (expires_at) WHERE is_active AND expires_at IS NOT NULL;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS evidence (
+    id SERIAL PRIMARY KEY,
+    bad_actor_id INT NOT NULL REFERENCES bad_actors(id) ON DELETE CASCADE,
+    file_name VARCHAR(100) NOT NULL,
+    position INT NOT NULL, -- 1-based order in which the evidence is displayed
+    added_by VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- deferred, so shifting the positions after a removed evidence doesn't collide midway
+    CONSTRAINT evidence_bad_actor_id_position_key UNIQUE (bad_actor_id, position) DEFERRABLE INITIALLY DEFERRED
+);
@@ -0,0 +1,10 @@
+-- content hash file names do not fit into 50 characters
+ALTER TABLE bad_actors ALTER COLUMN screenshot_proof TYPE VARCHAR(100);
+
+CREATE TABLE IF NOT EXISTS stored_files (
+    file_name VARCHAR(100) PRIMARY KEY,
+    sha256 CHAR(64) NOT NULL,
+    mime_type VARCHAR(50) NOT NULL,
+    size INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
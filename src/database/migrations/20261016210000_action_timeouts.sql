@@ -0,0 +1,6 @@
+-- timeout length in minutes for the timeout action level, 7 days like before
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS spam_action_timeout INT NOT NULL DEFAULT 10080,
+    ADD COLUMN IF NOT EXISTS impersonation_action_timeout INT NOT NULL DEFAULT 10080,
+    ADD COLUMN IF NOT EXISTS bigotry_action_timeout INT NOT NULL DEFAULT 10080,
+    ADD COLUMN IF NOT EXISTS honeypot_action_timeout INT NOT NULL DEFAULT 10080;
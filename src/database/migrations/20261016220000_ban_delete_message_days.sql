@@ -0,0 +1,3 @@
+-- days of messages Discord deletes when Janitor bans or softbans, at most 7
+ALTER TABLE server_configs
+    ADD COLUMN IF NOT EXISTS ban_delete_message_days INT NOT NULL DEFAULT 7;
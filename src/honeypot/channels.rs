@@ -1,31 +1,34 @@
+use anyhow::Context;
 use dashmap::DashSet;
 use serenity::all::ChannelId;
 use sqlx::PgPool;
-use std::{str::FromStr, sync::Arc};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 
 pub type HoneypotChannels = Arc<DashSet<ChannelId>>;
 
-pub async fn populate_honeypot_channels(channels: &HoneypotChannels, db_pool: &PgPool) {
-    channels.clear();
-
+pub async fn populate_honeypot_channels(
+    channels: &HoneypotChannels,
+    db_pool: &PgPool,
+) -> anyhow::Result<()> {
     let snowflakes =
         sqlx::query_scalar::<_, Option<String>>("SELECT honeypot_channel_id FROM server_configs;")
             .fetch_all(db_pool)
             .await
-            .expect("Failed to get the honeypot channel ids from the database");
-
-    if snowflakes.is_empty() {
-        return;
-    }
+            .context("Failed to get the honeypot channel ids from the database")?;
 
-    for snowflake in snowflakes {
-        let Some(snowflake) = snowflake else {
-            continue;
-        };
+    let channel_ids = snowflakes
+        .into_iter()
+        .flatten()
+        .map(|snowflake| ChannelId::from_str(&snowflake))
+        .collect::<Result<HashSet<_>, _>>()
+        .context("Failed to parse honeypot channel id snowflake from database")?;
 
-        let channel_id = ChannelId::from_str(&snowflake)
-            .expect("Failed to parse honeypot channel id snowflake from database");
+    // never clear the set, messages are checked against it while it is refreshed
+    channels.retain(|channel_id| channel_ids.contains(channel_id));
 
+    for channel_id in channel_ids {
         channels.insert(channel_id);
     }
+
+    Ok(())
 }
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use dashmap::DashMap;
+use serenity::all::GuildId;
+use sqlx::PgPool;
+
+use crate::database::controllers::serverconfig_model_controller::ServerConfigModelController;
+
+pub type HoneypotHeuristicsCache = Arc<DashMap<GuildId, HoneypotHeuristics>>;
+
+/// How two messages are compared to decide if they have the same content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+#[repr(i8)]
+pub enum ContentSimilarity {
+    #[default]
+    Exact,
+    Normalized,
+    #[name = "URL Only"]
+    UrlOnly,
+    Fuzzy,
+}
+
+impl std::fmt::Display for ContentSimilarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact => write!(f, "exact"),
+            Self::Normalized => write!(f, "normalized"),
+            Self::UrlOnly => write!(f, "url only"),
+            Self::Fuzzy => write!(f, "fuzzy"),
+        }
+    }
+}
+
+impl TryFrom<i32> for ContentSimilarity {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Exact),
+            1 => Ok(Self::Normalized),
+            2 => Ok(Self::UrlOnly),
+            3 => Ok(Self::Fuzzy),
+            _ => {
+                anyhow::bail!("Unknown content similarity: {value}")
+            }
+        }
+    }
+}
+
+/// Maximum edit distance relative to the longer message for two messages to count as similar in fuzzy mode.
+const FUZZY_MAX_DISTANCE_RATIO: f64 = 0.2;
+/// Fuzzy mode only compares the start of long messages, so comparing them stays cheap.
+const FUZZY_MAX_CHARS: usize = 500;
+
+/// A message content prepared once to be compared against many other messages.
+pub struct ComparableContent<'a> {
+    raw: &'a str,
+    normalized: String,
+}
+
+impl<'a> ComparableContent<'a> {
+    pub fn new(raw: &'a str) -> Self {
+        Self {
+            raw,
+            normalized: normalize(raw),
+        }
+    }
+}
+
+impl ContentSimilarity {
+    pub fn is_similar(&self, a: &str, b: &str) -> bool {
+        self.is_similar_to(a, &ComparableContent::new(b))
+    }
+
+    pub fn is_similar_to(&self, a: &str, b: &ComparableContent) -> bool {
+        match self {
+            Self::Exact => a == b.raw,
+            Self::Normalized => normalize(a) == b.normalized,
+            Self::UrlOnly => {
+                let urls_a = extract_urls(a);
+                !urls_a.is_empty() && urls_a == extract_urls(b.raw)
+            }
+            Self::Fuzzy => {
+                let a = normalize(a)
+                    .chars()
+                    .take(FUZZY_MAX_CHARS)
+                    .collect::<Vec<_>>();
+                let b = b
+                    .normalized
+                    .chars()
+                    .take(FUZZY_MAX_CHARS)
+                    .collect::<Vec<_>>();
+                let longest = a.len().max(b.len());
+
+                if longest == 0 {
+                    return true;
+                }
+
+                let max_distance = (longest as f64 * FUZZY_MAX_DISTANCE_RATIO) as usize;
+
+                bounded_levenshtein(&a, &b, max_distance).is_some()
+            }
+        }
+    }
+}
+
+/// Normalized messages shorter than this only count towards cross-server detection if they contain a URL.
+const CROSS_SERVER_MIN_CONTENT_LEN: usize = 20;
+
+/// Whether a message is distinct enough to count towards cross-server detection.
+/// Empty messages, e.g. image or sticker only posts, and short common text like "gm" never count.
+pub fn counts_cross_server(content: &str) -> bool {
+    let normalized = normalize(content);
+
+    if normalized.is_empty() {
+        return false;
+    }
+
+    normalized.chars().count() >= CROSS_SERVER_MIN_CONTENT_LEN || !extract_urls(content).is_empty()
+}
+
+/// The per-guild settings that decide when a user gets reported by the honeypot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoneypotHeuristics {
+    /// How long messages are remembered for.
+    pub window: Duration,
+    /// How many distinct channels the same content has to be posted in.
+    pub min_channels: usize,
+    /// Report on a single message in the honeypot channel, regardless of the other messages.
+    pub single_hit: bool,
+    pub similarity: ContentSimilarity,
+    /// Report users that post the same message in multiple servers that opted into this.
+    pub cross_server: bool,
+}
+
+impl Default for HoneypotHeuristics {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            min_channels: 3,
+            single_hit: false,
+            similarity: ContentSimilarity::Exact,
+            cross_server: false,
+        }
+    }
+}
+
+pub async fn populate_honeypot_heuristics(
+    cache: &HoneypotHeuristicsCache,
+    db_pool: &PgPool,
+) -> anyhow::Result<()> {
+    let server_configs = ServerConfigModelController::get_all(db_pool)
+        .await
+        .context(
+            "Failed to get the server configs for the honeypot heuristics from the database",
+        )?;
+
+    let heuristics = server_configs
+        .into_iter()
+        .map(|c| (c.guild_id, c.honeypot_heuristics()))
+        .collect::<HashMap<_, _>>();
+
+    // never clear the cache, messages are checked against it while it is refreshed
+    cache.retain(|guild_id, _| heuristics.contains_key(guild_id));
+
+    for (guild_id, guild_heuristics) in heuristics {
+        cache.insert(guild_id, guild_heuristics);
+    }
+
+    Ok(())
+}
+
+/// Lowercases the input and collapses all whitespace into single spaces.
+fn normalize(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn extract_urls(input: &str) -> Vec<String> {
+    let mut urls = input
+        .split_whitespace()
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|url| {
+            url.trim_end_matches(['/', '.', ',', ')', '>'])
+                .to_lowercase()
+        })
+        .collect::<Vec<_>>();
+
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// The edit distance between `a` and `b`, or `None` as soon as it is certain to be greater than `max`.
+/// Only the cells within `max` of the diagonal are computed, since no cheaper edit path leaves that band.
+fn bounded_levenshtein(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    // anything greater than `max` is the same to us, so distances are capped here
+    let over = max + 1;
+    let mut previous = (0..=b.len()).map(|j| j.min(over)).collect::<Vec<_>>();
+    let mut current = vec![over; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        let row = i + 1;
+        let from = row.saturating_sub(max).max(1);
+        let to = (row + max).min(b.len());
+
+        current[from - 1] = if from == 1 { row.min(over) } else { over };
+        let mut row_min = current[from - 1];
+
+        for j in from..=to {
+            let substitution = previous[j - 1] + usize::from(*ca != b[j - 1]);
+            current[j] = substitution
+                .min(previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(over);
+            row_min = row_min.min(current[j]);
+        }
+
+        if to < b.len() {
+            current[to + 1] = over;
+        }
+
+        if row_min > max {
+            return None;
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levenshtein(a: &[char], b: &[char]) -> usize {
+        let mut previous = (0..=b.len()).collect::<Vec<_>>();
+        let mut current = vec![0; b.len() + 1];
+
+        for (i, ca) in a.iter().enumerate() {
+            current[0] = i + 1;
+
+            for (j, cb) in b.iter().enumerate() {
+                let substitution = previous[j] + usize::from(ca != cb);
+                current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            }
+
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        previous[b.len()]
+    }
+
+    #[test]
+    fn bounded_distance_matches_the_full_distance() {
+        let words = [
+            "",
+            "a",
+            "ab",
+            "abc",
+            "free nitro",
+            "free nitr0",
+            "fre nitro!",
+            "nitro free",
+            "xyz",
+        ];
+
+        for a in words {
+            for b in words {
+                let a = a.chars().collect::<Vec<_>>();
+                let b = b.chars().collect::<Vec<_>>();
+                let distance = levenshtein(&a, &b);
+
+                for max in 0..12 {
+                    let expected = (distance <= max).then_some(distance);
+                    assert_eq!(
+                        bounded_levenshtein(&a, &b, max),
+                        expected,
+                        "{a:?} {b:?} {max}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fuzzy_compares_only_the_start_of_long_messages() {
+        let spam = "claim your free nitro at https://scam.example ".repeat(100);
+        let similar = format!("{spam} and more");
+
+        assert!(ContentSimilarity::Fuzzy.is_similar(&spam, &similar));
+        assert!(!ContentSimilarity::Fuzzy.is_similar(&spam, "claim your free nitro"));
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use chrono::Utc;
@@ -9,21 +7,19 @@ use serenity::{
     CreateMessage, GuildChannel, GuildId, Message, PartialGuild, Timestamp, User, UserId,
 };
 use sqlx::PgPool;
-use tokio::sync::{Mutex, MutexGuard};
 
 use crate::broadcast::broadcast_handler::{broadcast, BroadcastOptions, BroadcastType};
 use crate::database::controllers::badactor_model_controller::{
     BadActor, BadActorModelController, BadActorType, CreateBadActorOptions,
 };
 use crate::database::controllers::serverconfig_model_controller::ServerConfigModelController;
+use crate::honeypot::tracker::Detection;
 use crate::util::config::Config;
 use crate::util::embeds::EmbedColor;
 use crate::util::format::{self, escape_markdown};
 use crate::util::logger::Logger;
 use crate::Data;
 
-pub type Queue = Arc<Mutex<Vec<HoneypotMessage>>>;
-
 #[derive(Debug)]
 pub struct HoneypotMessage {
     pub guild_id: GuildId,
@@ -32,11 +28,15 @@ pub struct HoneypotMessage {
     pub content: String,
     pub timestamp: Instant,
     pub is_in_honeypot: bool,
+    /// How long this message is remembered for, taken from its guild's honeypot heuristics.
+    pub window: Duration,
+    /// If the guild of this message opted into cross-server detection.
+    pub cross_server: bool,
 }
 
 #[derive(Debug)]
 struct MaybeReportBadActorOptions<'a> {
-    should_report: bool,
+    detection: Option<Detection>,
     db_pool: &'a PgPool,
     config: &'a Config,
     target_user: &'a User,
@@ -66,10 +66,17 @@ pub async fn handle_message(
         delete_msg_from_honeypot(&ctx, &ctx, &framework.user_data.db_pool, msg, guild_id).await;
     }
 
-    let mut queue = framework.user_data.queue.lock().await;
+    let heuristics = framework
+        .user_data
+        .honeypot_heuristics
+        .get(&guild_id)
+        .map(|h| *h)
+        .unwrap_or_default();
+
+    let message_tracker = &framework.user_data.message_tracker;
     let now = Instant::now();
 
-    let removed_honeypot_messages = remove_old_messages(&mut queue, now);
+    let mut removed_honeypot_messages = message_tracker.remove_old_messages(now);
 
     let new_honeypot_msg = HoneypotMessage {
         guild_id,
@@ -78,16 +85,16 @@ pub async fn handle_message(
         is_in_honeypot,
         channel_id: msg.channel_id,
         timestamp: now,
+        window: heuristics.window,
+        cross_server: heuristics.cross_server,
     };
 
-    let should_report = should_report(&queue, &new_honeypot_msg);
-    queue.push(new_honeypot_msg);
-
-    // drop the MutexGuard which unlocks the mutex again
-    drop(queue);
+    let (detection, dropped_honeypot_messages) =
+        message_tracker.track(new_honeypot_msg, &heuristics);
+    removed_honeypot_messages.extend(dropped_honeypot_messages);
 
     let report_options = MaybeReportBadActorOptions {
-        should_report,
+        detection,
         db_pool: &framework.user_data.db_pool,
         config: &framework.user_data.config,
         target_user: &msg.author,
@@ -105,50 +112,12 @@ pub async fn handle_message(
     tokio::join!(report_future, timeout_future);
 }
 
-// Removes all messages that er older than 1 minute from the queue and returns all messages there were sent in the honeypot channel.
-// We need this to find out who to timeout.
-fn remove_old_messages(
-    queue: &mut MutexGuard<'_, Vec<HoneypotMessage>>,
-    now: Instant,
-) -> Vec<HoneypotMessage> {
-    let first_new_msg = queue
-        .iter()
-        .enumerate()
-        .find(|(_, msg)| now - msg.timestamp < Duration::from_secs(60))
-        .map(|(i, _)| i)
-        .unwrap_or(queue.len());
-
-    queue
-        .drain(..first_new_msg)
-        .filter(|msg| msg.is_in_honeypot)
-        .collect::<Vec<_>>()
-}
-
-fn should_report(queue: &MutexGuard<'_, Vec<HoneypotMessage>>, new_msg: &HoneypotMessage) -> bool {
-    let mut is_any_in_honeypot = new_msg.is_in_honeypot;
-
-    let mut seen_channel_ids = Vec::with_capacity(3);
-
-    seen_channel_ids.push(new_msg.channel_id);
-
-    for queue_msg in queue.iter() {
-        if queue_msg.user_id == new_msg.user_id && queue_msg.content == new_msg.content {
-            if !seen_channel_ids.contains(&queue_msg.channel_id) {
-                is_any_in_honeypot |= queue_msg.is_in_honeypot;
-                seen_channel_ids.push(queue_msg.channel_id);
-            }
-        }
-    }
-
-    seen_channel_ids.len() >= 3 && is_any_in_honeypot
-}
-
 async fn maybe_report_bad_actor(
     cache_http: impl CacheHttp,
     options: MaybeReportBadActorOptions<'_>,
 ) {
     let MaybeReportBadActorOptions {
-        should_report,
+        detection,
         db_pool,
         config,
         target_user,
@@ -156,18 +125,32 @@ async fn maybe_report_bad_actor(
         bot_id,
     } = options;
 
-    if should_report {
+    if let Some(detection) = detection {
         if has_active_case(&cache_http, db_pool, target_user).await {
             return;
         }
 
+        let (actor_type, explanation, broadcast_type) = match detection {
+            Detection::Honeypot => (
+                BadActorType::Honeypot,
+                "reached into the honeypot",
+                BroadcastType::Honeypot,
+            ),
+            Detection::CrossServer => (
+                BadActorType::Spam,
+                "posted the same message in multiple servers",
+                BroadcastType::Report,
+            ),
+        };
+
         let bad_actor_options = CreateBadActorOptions {
             user_id: target_user.id,
-            actor_type: BadActorType::Honeypot,
+            actor_type,
             screenshot_proof: None,
-            explanation: Some("reached into the honeypot".to_string()),
+            explanation: Some(explanation.to_string()),
             origin_guild_id,
             updated_by_user_id: bot_id,
+            expires_at: None,
         };
 
         let bad_actor_future = save_bad_actor(&cache_http, db_pool, target_user, bad_actor_options);
@@ -194,7 +177,7 @@ async fn maybe_report_bad_actor(
             bad_actor_user: target_user,
             origin_guild,
             origin_guild_id,
-            broadcast_type: BroadcastType::Honeypot,
+            broadcast_type,
         };
 
         broadcast(&cache_http, broadcast_options).await;
@@ -310,7 +293,7 @@ async fn timeout_honeypot_trolls(
 async fn has_active_case(cache_http: impl CacheHttp, db_pool: &PgPool, target_user: &User) -> bool {
     if BadActorModelController::has_active_case(db_pool, target_user.id).await {
         let msg = format!(
-            "User {} reached into a honeypot but already has an active case. Skipping report.",
+            "User {} was caught by the spam detection but already has an active case. Skipping report.",
             format::display(target_user)
         );
         Logger::get().warn(cache_http, msg).await;
@@ -331,7 +314,7 @@ async fn save_bad_actor(
         Ok(bad_actor) => Ok(bad_actor),
         Err(e) => {
             let log_msg = format!(
-                "Failed to add bad actor {} into the database after honeypot triggered.",
+                "Failed to add bad actor {} into the database after the spam detection triggered.",
                 format::display(target_user)
             );
             Logger::get().error(cache_http, &e, log_msg).await;
@@ -1,2 +1,4 @@
 pub mod channels;
+pub mod heuristics;
 pub mod message;
+pub mod tracker;
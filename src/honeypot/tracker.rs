@@ -0,0 +1,551 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, TryLockError};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serenity::all::UserId;
+
+use super::heuristics::{counts_cross_server, ComparableContent, HoneypotHeuristics};
+use super::message::HoneypotMessage;
+
+/// The maximum amount of messages that are remembered per user. Older messages are dropped first.
+const MAX_MESSAGES_PER_USER: usize = 50;
+/// How many independently locked sets of expiry queues there are. Users are assigned to one by their ID.
+const EXPIRY_SHARDS: usize = 16;
+
+/// Remembers recent messages per user for honeypot detection.
+///
+/// Messages are stored in a [DashMap] keyed by the author, so messages from different users do not contend for the same lock.
+/// Expiry is tracked in one FIFO per window length. All entries of a FIFO share the same window,
+/// so they expire in insertion order and expired entries are always at the front.
+/// The FIFOs are sharded by user, so tracking a message only locks the shard of its author
+/// and removing old messages skips shards that are busy instead of waiting for them.
+#[derive(Debug)]
+pub struct MessageTracker {
+    users: DashMap<UserId, VecDeque<HoneypotMessage>>,
+    expiry_shards: Vec<Mutex<Vec<ExpiryQueue>>>,
+    cross_server_min_guilds: usize,
+}
+
+/// Why a user should be reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// The same message was posted in multiple channels, including a honeypot channel.
+    Honeypot,
+    /// The same message was posted in multiple servers that opted into cross-server detection.
+    CrossServer,
+}
+
+#[derive(Debug)]
+struct ExpiryQueue {
+    window: Duration,
+    entries: VecDeque<(Instant, UserId)>,
+}
+
+impl MessageTracker {
+    pub fn new(cross_server_min_guilds: usize) -> Self {
+        Self {
+            users: DashMap::new(),
+            expiry_shards: (0..EXPIRY_SHARDS).map(|_| Mutex::new(Vec::new())).collect(),
+            cross_server_min_guilds,
+        }
+    }
+
+    /// Removes all messages that are older than the window of their guild and returns all of those that were sent in a honeypot channel.
+    /// We need this to find out who to timeout.
+    /// Shards that are locked by another message are skipped, their messages are removed by the next call.
+    pub fn remove_old_messages(&self, now: Instant) -> Vec<HoneypotMessage> {
+        let mut expired_users = Vec::new();
+
+        for shard in &self.expiry_shards {
+            let mut expiry_queues = match shard.try_lock() {
+                Ok(expiry_queues) => expiry_queues,
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Poisoned(_)) => {
+                    panic!("Honeypot expiry queue lock should not be poisoned")
+                }
+            };
+
+            for queue in expiry_queues.iter_mut() {
+                while let Some(&(timestamp, user_id)) = queue.entries.front() {
+                    if now - timestamp < queue.window {
+                        break;
+                    }
+
+                    queue.entries.pop_front();
+                    expired_users.push(user_id);
+                }
+            }
+
+            expiry_queues.retain(|queue| !queue.entries.is_empty());
+        }
+
+        let mut removed = Vec::new();
+
+        for user_id in expired_users {
+            self.users.remove_if_mut(&user_id, |_, messages| {
+                let (old, new): (VecDeque<_>, VecDeque<_>) = messages
+                    .drain(..)
+                    .partition(|msg| now - msg.timestamp >= msg.window);
+
+                *messages = new;
+                removed.extend(old.into_iter().filter(|msg| msg.is_in_honeypot));
+
+                messages.is_empty()
+            });
+        }
+
+        removed
+    }
+
+    /// Checks if the new message should get its author reported and remembers it afterwards.
+    /// Returns the honeypot messages that had to be dropped to stay within the per-user limit alongside the result.
+    pub fn track(
+        &self,
+        new_msg: HoneypotMessage,
+        heuristics: &HoneypotHeuristics,
+    ) -> (Option<Detection>, Vec<HoneypotMessage>) {
+        let user_id = new_msg.user_id;
+        let timestamp = new_msg.timestamp;
+        let window = new_msg.window;
+
+        let (detection, dropped) = {
+            let mut messages = self.users.entry(user_id).or_default();
+            let new_content = ComparableContent::new(&new_msg.content);
+
+            let detection = if should_report(&messages, &new_msg, &new_content, heuristics) {
+                Some(Detection::Honeypot)
+            } else if should_report_cross_server(
+                &messages,
+                &new_msg,
+                &new_content,
+                heuristics,
+                self.cross_server_min_guilds,
+            ) {
+                Some(Detection::CrossServer)
+            } else {
+                None
+            };
+
+            messages.push_back(new_msg);
+
+            let mut dropped = Vec::new();
+
+            while messages.len() > MAX_MESSAGES_PER_USER {
+                if let Some(msg) = messages.pop_front() {
+                    if msg.is_in_honeypot {
+                        dropped.push(msg);
+                    }
+                }
+            }
+
+            (detection, dropped)
+        };
+
+        let mut expiry_queues = self.expiry_shards[user_id.get() as usize % EXPIRY_SHARDS]
+            .lock()
+            .expect("Honeypot expiry queue lock should not be poisoned");
+
+        match expiry_queues.iter_mut().find(|q| q.window == window) {
+            Some(queue) => queue.entries.push_back((timestamp, user_id)),
+            None => expiry_queues.push(ExpiryQueue {
+                window,
+                entries: VecDeque::from([(timestamp, user_id)]),
+            }),
+        }
+
+        (detection, dropped)
+    }
+}
+
+fn should_report(
+    messages: &VecDeque<HoneypotMessage>,
+    new_msg: &HoneypotMessage,
+    new_content: &ComparableContent,
+    heuristics: &HoneypotHeuristics,
+) -> bool {
+    if heuristics.single_hit && new_msg.is_in_honeypot {
+        return true;
+    }
+
+    let mut is_any_in_honeypot = new_msg.is_in_honeypot;
+
+    let mut seen_channel_ids = Vec::with_capacity(heuristics.min_channels);
+
+    seen_channel_ids.push(new_msg.channel_id);
+
+    for msg in messages.iter() {
+        if new_msg.timestamp - msg.timestamp < heuristics.window
+            && !seen_channel_ids.contains(&msg.channel_id)
+            && heuristics
+                .similarity
+                .is_similar_to(&msg.content, new_content)
+        {
+            is_any_in_honeypot |= msg.is_in_honeypot;
+            seen_channel_ids.push(msg.channel_id);
+        }
+    }
+
+    seen_channel_ids.len() >= heuristics.min_channels && is_any_in_honeypot
+}
+
+/// Checks if the same message was posted in enough distinct servers that opted into cross-server detection.
+/// The window and similarity of the new message's guild are used for the messages from other guilds too,
+/// since that guild's member is the one being evaluated.
+fn should_report_cross_server(
+    messages: &VecDeque<HoneypotMessage>,
+    new_msg: &HoneypotMessage,
+    new_content: &ComparableContent,
+    heuristics: &HoneypotHeuristics,
+    min_guilds: usize,
+) -> bool {
+    if !new_msg.cross_server || !counts_cross_server(&new_msg.content) {
+        return false;
+    }
+
+    let mut seen_guild_ids = Vec::with_capacity(min_guilds);
+
+    seen_guild_ids.push(new_msg.guild_id);
+
+    for msg in messages.iter() {
+        if msg.cross_server
+            && counts_cross_server(&msg.content)
+            && new_msg.timestamp - msg.timestamp < heuristics.window
+            && !seen_guild_ids.contains(&msg.guild_id)
+            && heuristics
+                .similarity
+                .is_similar_to(&msg.content, new_content)
+        {
+            seen_guild_ids.push(msg.guild_id);
+        }
+    }
+
+    seen_guild_ids.len() >= min_guilds
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::{ChannelId, GuildId};
+
+    use super::*;
+    use crate::honeypot::heuristics::ContentSimilarity;
+
+    /// The detection from before the tracker existed, a single queue that is scanned linearly.
+    mod legacy {
+        use super::*;
+
+        pub fn remove_old_messages(
+            queue: &mut Vec<HoneypotMessage>,
+            now: Instant,
+        ) -> Vec<HoneypotMessage> {
+            let (old, new): (Vec<_>, Vec<_>) = queue
+                .drain(..)
+                .partition(|msg| now - msg.timestamp >= msg.window);
+
+            *queue = new;
+
+            old.into_iter()
+                .filter(|msg| msg.is_in_honeypot)
+                .collect::<Vec<_>>()
+        }
+
+        pub fn should_report(
+            queue: &[HoneypotMessage],
+            new_msg: &HoneypotMessage,
+            heuristics: &HoneypotHeuristics,
+        ) -> bool {
+            if heuristics.single_hit && new_msg.is_in_honeypot {
+                return true;
+            }
+
+            let mut is_any_in_honeypot = new_msg.is_in_honeypot;
+
+            let mut seen_channel_ids = Vec::with_capacity(heuristics.min_channels);
+
+            seen_channel_ids.push(new_msg.channel_id);
+
+            for queue_msg in queue.iter() {
+                if queue_msg.user_id == new_msg.user_id
+                    && new_msg.timestamp - queue_msg.timestamp < heuristics.window
+                    && !seen_channel_ids.contains(&queue_msg.channel_id)
+                    && heuristics
+                        .similarity
+                        .is_similar(&queue_msg.content, &new_msg.content)
+                {
+                    is_any_in_honeypot |= queue_msg.is_in_honeypot;
+                    seen_channel_ids.push(queue_msg.channel_id);
+                }
+            }
+
+            seen_channel_ids.len() >= heuristics.min_channels && is_any_in_honeypot
+        }
+    }
+
+    /// Small deterministic pseudo random number generator so the tests do not need extra dependencies.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+    }
+
+    fn message(
+        start: Instant,
+        secs: u64,
+        user: u64,
+        channel: u64,
+        content: &str,
+        window: u64,
+    ) -> HoneypotMessage {
+        HoneypotMessage {
+            guild_id: GuildId::new(1),
+            user_id: UserId::new(user),
+            channel_id: ChannelId::new(channel),
+            content: content.to_string(),
+            timestamp: start + Duration::from_secs(secs),
+            is_in_honeypot: channel == 1,
+            window: Duration::from_secs(window),
+            cross_server: false,
+        }
+    }
+
+    fn copy(msg: &HoneypotMessage) -> HoneypotMessage {
+        HoneypotMessage {
+            guild_id: msg.guild_id,
+            user_id: msg.user_id,
+            channel_id: msg.channel_id,
+            content: msg.content.clone(),
+            timestamp: msg.timestamp,
+            is_in_honeypot: msg.is_in_honeypot,
+            window: msg.window,
+            cross_server: msg.cross_server,
+        }
+    }
+
+    fn sorted_keys(messages: &[HoneypotMessage]) -> Vec<(Instant, UserId, ChannelId)> {
+        let mut keys = messages
+            .iter()
+            .map(|m| (m.timestamp, m.user_id, m.channel_id))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    /// Feeds the same message stream into the legacy queue and the tracker and compares every result.
+    fn assert_parity(seed: u64, heuristics_options: &[HoneypotHeuristics]) {
+        let start = Instant::now();
+        let mut rng = Lcg(seed);
+        let contents = [
+            "free nitro https://scam.example",
+            "hello",
+            "Free  Nitro https://scam.example",
+        ];
+
+        let mut legacy_queue = Vec::new();
+        let tracker = MessageTracker::new(3);
+        let mut secs = 0;
+
+        for _ in 0..500 {
+            secs += rng.next(8);
+            let heuristics = heuristics_options[rng.next(heuristics_options.len() as u64) as usize];
+
+            let new_msg = message(
+                start,
+                secs,
+                rng.next(4) + 1,
+                rng.next(5) + 1,
+                contents[rng.next(contents.len() as u64) as usize],
+                heuristics.window.as_secs(),
+            );
+            let now = new_msg.timestamp;
+
+            let legacy_removed = legacy::remove_old_messages(&mut legacy_queue, now);
+            let removed = tracker.remove_old_messages(now);
+            assert_eq!(sorted_keys(&legacy_removed), sorted_keys(&removed));
+
+            let legacy_report = legacy::should_report(&legacy_queue, &new_msg, &heuristics);
+            legacy_queue.push(copy(&new_msg));
+
+            let (detection, dropped) = tracker.track(new_msg, &heuristics);
+            assert!(dropped.is_empty());
+            assert_eq!(legacy_report, detection == Some(Detection::Honeypot));
+        }
+    }
+
+    #[test]
+    fn parity_with_default_heuristics() {
+        for seed in 0..20 {
+            assert_parity(seed, &[HoneypotHeuristics::default()]);
+        }
+    }
+
+    #[test]
+    fn parity_with_mixed_heuristics() {
+        let options = [
+            HoneypotHeuristics::default(),
+            HoneypotHeuristics {
+                window: Duration::from_secs(20),
+                min_channels: 2,
+                single_hit: false,
+                similarity: ContentSimilarity::Normalized,
+                cross_server: false,
+            },
+            HoneypotHeuristics {
+                window: Duration::from_secs(120),
+                min_channels: 3,
+                single_hit: true,
+                similarity: ContentSimilarity::UrlOnly,
+                cross_server: false,
+            },
+        ];
+
+        for seed in 0..20 {
+            assert_parity(seed, &options);
+        }
+    }
+
+    #[test]
+    fn reports_same_content_in_three_channels() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics::default();
+
+        let (first, _) = tracker.track(message(start, 0, 7, 1, "spam", 60), &heuristics);
+        let (second, _) = tracker.track(message(start, 1, 7, 2, "spam", 60), &heuristics);
+        let (third, _) = tracker.track(message(start, 2, 7, 3, "spam", 60), &heuristics);
+
+        assert_eq!(first, None);
+        assert_eq!(second, None);
+        assert_eq!(third, Some(Detection::Honeypot));
+    }
+
+    #[test]
+    fn reports_same_content_in_opted_in_servers() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics {
+            cross_server: true,
+            ..Default::default()
+        };
+
+        let in_guild = |secs: u64, guild: u64, cross_server: bool| HoneypotMessage {
+            guild_id: GuildId::new(guild),
+            channel_id: ChannelId::new(guild + 100),
+            cross_server,
+            ..message(start, secs, 7, 2, "free nitro https://scam.example", 60)
+        };
+
+        let (first, _) = tracker.track(in_guild(0, 1, true), &heuristics);
+        let (opted_out, _) = tracker.track(in_guild(1, 2, false), &heuristics);
+        let (second, _) = tracker.track(in_guild(2, 3, true), &heuristics);
+        let (third, _) = tracker.track(in_guild(3, 4, true), &heuristics);
+
+        assert_eq!(first, None);
+        assert_eq!(opted_out, None);
+        assert_eq!(second, None);
+        assert_eq!(third, Some(Detection::CrossServer));
+    }
+
+    #[test]
+    fn ignores_empty_and_short_content_across_servers() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics {
+            cross_server: true,
+            similarity: ContentSimilarity::Fuzzy,
+            ..Default::default()
+        };
+
+        for (user, content) in [(7, ""), (8, "   "), (9, "gm")] {
+            let detections = (1..=4)
+                .map(|guild| {
+                    let msg = HoneypotMessage {
+                        guild_id: GuildId::new(guild),
+                        channel_id: ChannelId::new(guild + 100),
+                        cross_server: true,
+                        ..message(start, guild, user, 2, content, 60)
+                    };
+                    tracker.track(msg, &heuristics).0
+                })
+                .collect::<Vec<_>>();
+
+            assert!(detections.iter().all(Option::is_none), "{content:?}");
+        }
+    }
+
+    #[test]
+    fn expires_messages_after_their_window() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics::default();
+
+        tracker.track(message(start, 0, 7, 1, "spam", 60), &heuristics);
+        tracker.track(message(start, 30, 7, 2, "spam", 60), &heuristics);
+
+        assert!(tracker
+            .remove_old_messages(start + Duration::from_secs(59))
+            .is_empty());
+
+        let removed = tracker.remove_old_messages(start + Duration::from_secs(60));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].channel_id, ChannelId::new(1));
+
+        tracker.remove_old_messages(start + Duration::from_secs(90));
+        assert!(tracker.users.is_empty());
+    }
+
+    #[test]
+    fn skips_busy_shards_until_the_next_removal() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics::default();
+
+        tracker.track(message(start, 0, 7, 1, "spam", 60), &heuristics);
+        tracker.track(message(start, 0, 8, 1, "spam", 60), &heuristics);
+
+        let later = start + Duration::from_secs(60);
+
+        {
+            let _busy = tracker.expiry_shards[7 % EXPIRY_SHARDS].lock().unwrap();
+
+            let removed = tracker.remove_old_messages(later);
+            assert_eq!(removed.len(), 1);
+            assert_eq!(removed[0].user_id, UserId::new(8));
+        }
+
+        let removed = tracker.remove_old_messages(later);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].user_id, UserId::new(7));
+    }
+
+    #[test]
+    fn bounds_messages_per_user() {
+        let start = Instant::now();
+        let tracker = MessageTracker::new(3);
+        let heuristics = HoneypotHeuristics::default();
+
+        let (_, dropped) = tracker.track(message(start, 0, 7, 1, "spam", 60), &heuristics);
+        assert!(dropped.is_empty());
+
+        for i in 0..MAX_MESSAGES_PER_USER {
+            let (_, dropped) =
+                tracker.track(message(start, 0, 7, 2, &i.to_string(), 60), &heuristics);
+
+            if i + 1 == MAX_MESSAGES_PER_USER {
+                assert_eq!(dropped.len(), 1);
+            } else {
+                assert!(dropped.is_empty());
+            }
+        }
+
+        assert_eq!(
+            tracker.users.get(&UserId::new(7)).unwrap().len(),
+            MAX_MESSAGES_PER_USER
+        );
+    }
+}
@@ -6,32 +6,40 @@ mod commands;
 mod database;
 mod honeypot;
 mod moderation;
+mod scheduler;
 mod util;
 
 use std::sync::Arc;
 
-use commands::{adminconfig, adminlist, badactor, config, scores, user};
-use dashmap::DashSet;
+use commands::{adminconfig, adminlist, appeal, badactor, config, scores, user, webhook};
+use dashmap::{DashMap, DashSet};
 use honeypot::channels::HoneypotChannels;
-use honeypot::message::{handle_message, Queue};
+use honeypot::heuristics::HoneypotHeuristicsCache;
+use honeypot::message::handle_message;
+use honeypot::tracker::MessageTracker;
 use moderation::interaction::handle_component_interaction;
 use poise::serenity_prelude as serenity;
+use scheduler::jobs::{FileGcJob, HoneypotCacheJob, OutboxJob, ReportExpiryJob, StaleWebhookJob};
+use scheduler::runner::{Job, JobContext, Scheduler};
 use serenity::InteractionType;
 use sqlx::postgres::PgPoolOptions;
 
-use tokio::sync::Mutex;
-use util::config::Config;
+use util::config::{Config, ScreenshotStorageConfig};
 use util::logger::Logger;
+use util::screenshot::{self, FileManager, FilesystemStorage};
 use util::{error, format};
 
 use crate::honeypot::channels::populate_honeypot_channels;
+use crate::honeypot::heuristics::populate_honeypot_heuristics;
 
 #[derive(Debug)]
 pub struct Data {
     pub db_pool: sqlx::PgPool,
     pub config: Config,
-    pub queue: Queue,
+    pub message_tracker: Arc<MessageTracker>,
     pub honeypot_channels: HoneypotChannels,
+    pub honeypot_heuristics: HoneypotHeuristicsCache,
+    pub scheduler: Arc<Scheduler>,
 }
 
 pub type AppContext<'a> = poise::Context<'a, Data, anyhow::Error>;
@@ -53,6 +61,23 @@ async fn main() -> anyhow::Result<()> {
         .await?;
     tracing::info!("Successfully connected to the database!");
 
+    database::migrate::run(&db_pool).await?;
+    tracing::info!("Successfully applied database migrations!");
+
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        tracing::info!("Started with --migrate-only, exiting.");
+        return Ok(());
+    }
+
+    FileManager::set_storage(screenshot::storage_from_config(&config.screenshot_storage));
+    tracing::info!("Successfully set up the screenshot storage!");
+
+    if std::env::args().any(|arg| arg == "--migrate-screenshots") {
+        migrate_screenshots(&config).await?;
+        tracing::info!("Started with --migrate-screenshots, exiting.");
+        return Ok(());
+    }
+
     let intents = serenity::GatewayIntents::GUILDS
         | serenity::GatewayIntents::GUILD_MODERATION
         | serenity::GatewayIntents::GUILD_MEMBERS
@@ -66,10 +91,12 @@ async fn main() -> anyhow::Result<()> {
             commands: vec![
                 adminconfig::adminconfig(),
                 adminlist::adminlist(),
+                appeal::appeal(),
                 config::config(),
                 scores::scores(),
                 user::user(),
                 badactor::badactor(),
+                webhook::webhook(),
             ],
             event_handler: |ctx, event, framework, _data| {
                 Box::pin(event_handler(ctx, event, framework))
@@ -87,14 +114,39 @@ async fn main() -> anyhow::Result<()> {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
 
-                let queue = Arc::new(Mutex::new(Vec::new()));
+                let message_tracker = Arc::new(MessageTracker::new(config.cross_server_min_guilds));
                 let honeypot_channels = Arc::new(DashSet::new());
+                let honeypot_heuristics = Arc::new(DashMap::new());
+
+                let job_ctx = JobContext {
+                    ctx: ctx.clone(),
+                    db_pool: db_pool.clone(),
+                };
+
+                let jobs: Vec<Box<dyn Job>> = vec![
+                    Box::new(OutboxJob),
+                    Box::new(ReportExpiryJob {
+                        config: config.clone(),
+                    }),
+                    Box::new(HoneypotCacheJob {
+                        honeypot_channels: honeypot_channels.clone(),
+                        honeypot_heuristics: honeypot_heuristics.clone(),
+                    }),
+                    Box::new(StaleWebhookJob),
+                    Box::new(FileGcJob),
+                ];
+
+                let scheduler = Arc::new(Scheduler::new(job_ctx, jobs));
+                scheduler.start();
+                tracing::info!("Successfully started the job scheduler!");
 
                 Ok(Data {
                     db_pool,
                     config,
-                    queue,
+                    message_tracker,
                     honeypot_channels,
+                    honeypot_heuristics,
+                    scheduler,
                 })
             })
         })
@@ -109,6 +161,27 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Copies the screenshots from the local `screenshots` directory to the configured storage.
+async fn migrate_screenshots(config: &Config) -> anyhow::Result<()> {
+    if let ScreenshotStorageConfig::Filesystem { directory } = &config.screenshot_storage {
+        if directory == "screenshots" {
+            anyhow::bail!("The screenshot storage is already the local `screenshots` directory");
+        }
+    }
+
+    let from = FilesystemStorage::new("screenshots");
+    let to = screenshot::storage_from_config(&config.screenshot_storage);
+
+    let summary = screenshot::copy_all(&from, to.as_ref()).await?;
+    tracing::info!(
+        "Copied {} screenshots, skipped {} that already existed.",
+        summary.copied,
+        summary.skipped
+    );
+
+    Ok(())
+}
+
 async fn event_handler(
     ctx: &serenity::Context,
     event: &serenity::FullEvent,
@@ -127,8 +200,12 @@ async fn event_handler(
             let db_pool = &framework.user_data.db_pool;
             let honeypot_channels = &framework.user_data.honeypot_channels;
 
-            populate_honeypot_channels(honeypot_channels, db_pool).await;
+            populate_honeypot_channels(honeypot_channels, db_pool).await?;
             tracing::info!("Successfully populated honeypot channels.");
+
+            let honeypot_heuristics = &framework.user_data.honeypot_heuristics;
+            populate_honeypot_heuristics(honeypot_heuristics, db_pool).await?;
+            tracing::info!("Successfully populated honeypot heuristics.");
         }
         serenity::FullEvent::InteractionCreate { interaction, .. } => {
             if interaction.kind() == InteractionType::Component {
@@ -140,6 +217,8 @@ async fn event_handler(
                     component_interaction,
                     ctx,
                     &framework.user_data.db_pool,
+                    &framework.user_data.config,
+                    framework.bot_id,
                 )
                 .await
                 {
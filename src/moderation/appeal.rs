@@ -0,0 +1,369 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use poise::serenity_prelude as serenity;
+use serenity::{
+    ButtonStyle, CacheHttp, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    CreateMessage, EditInteractionResponse, User, UserId,
+};
+use sqlx::PgPool;
+
+use crate::broadcast::broadcast_handler::{self, BroadcastOptions, BroadcastType};
+use crate::database::controllers::admin_model_controller::AdminModelController;
+use crate::database::controllers::appeal_model_controller::{
+    Appeal, AppealModelController, AppealStatus,
+};
+use crate::database::controllers::badactor_model_controller::{BadActor, BadActorModelController};
+use crate::util::config::Config;
+use crate::util::embeds::EmbedColor;
+use crate::util::{format, locks, logger::Logger};
+
+/// The buttons on an appeal message in the admin server. The appeal ID is part of the custom ID.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AppealCustomId {
+    Accept(i32),
+    Reject(i32),
+}
+
+impl FromStr for AppealCustomId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((action, appeal_id)) = s.split_once(':') else {
+            anyhow::bail!("Unknown appeal custom id {s}");
+        };
+
+        let appeal_id = appeal_id.parse::<i32>()?;
+
+        match action {
+            "appeal_accept" => Ok(Self::Accept(appeal_id)),
+            "appeal_reject" => Ok(Self::Reject(appeal_id)),
+            _ => anyhow::bail!("Unknown appeal custom id {s}"),
+        }
+    }
+}
+
+impl Display for AppealCustomId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Accept(appeal_id) => write!(f, "appeal_accept:{appeal_id}"),
+            Self::Reject(appeal_id) => write!(f, "appeal_reject:{appeal_id}"),
+        }
+    }
+}
+
+impl AppealCustomId {
+    fn appeal_id(&self) -> i32 {
+        match self {
+            Self::Accept(appeal_id) | Self::Reject(appeal_id) => *appeal_id,
+        }
+    }
+
+    fn status(&self) -> AppealStatus {
+        match self {
+            Self::Accept(_) => AppealStatus::Accepted,
+            Self::Reject(_) => AppealStatus::Rejected,
+        }
+    }
+}
+
+pub struct HandleAppealOptions<'a> {
+    pub custom_id: AppealCustomId,
+    pub db_pool: &'a PgPool,
+    pub config: &'a Config,
+    pub bot_id: UserId,
+}
+
+struct BroadcastDeactivationOptions<'a> {
+    deactivated: &'a BadActor,
+    appellant: &'a User,
+    acting_user: &'a User,
+    config: &'a Config,
+    db_pool: &'a PgPool,
+    bot_id: UserId,
+}
+
+/// The message posted to the admin server log channel when a user submits an appeal.
+pub fn get_appeal_message(
+    appeal: &Appeal,
+    appellant: &User,
+    bad_actor: &BadActor,
+) -> CreateMessage {
+    let embed = get_appeal_embed(appeal, appellant, bad_actor, EmbedColor::Blue);
+
+    let buttons = vec![
+        CreateButton::new(AppealCustomId::Accept(appeal.id).to_string())
+            .label("Accept")
+            .style(ButtonStyle::Success),
+        CreateButton::new(AppealCustomId::Reject(appeal.id).to_string())
+            .label("Reject")
+            .style(ButtonStyle::Danger),
+    ];
+
+    CreateMessage::new()
+        .content("A reported user has submitted an appeal.")
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(buttons)])
+}
+
+fn get_appeal_embed(
+    appeal: &Appeal,
+    appellant: &User,
+    bad_actor: &BadActor,
+    colour: EmbedColor,
+) -> CreateEmbed {
+    CreateEmbed::default()
+        .title(format!("Appeal {}", appeal.id))
+        .color(colour)
+        .timestamp(appeal.created_at)
+        .field("User", format::fdisplay(appellant), false)
+        .field("Report ID", bad_actor.id.to_string(), true)
+        .field("Type", bad_actor.actor_type.to_string(), true)
+        .field("Status", appeal.status.to_string(), true)
+        .field("Appeal", format::truncate(&appeal.content, 1024), false)
+}
+
+/// Accepts or rejects an appeal from the buttons on its message in the admin server.
+/// Accepting deactivates the bad actor entry and broadcasts the deactivation.
+pub async fn handle_appeal_interaction(
+    interaction: &ComponentInteraction,
+    cache_http: impl CacheHttp,
+    options: HandleAppealOptions<'_>,
+) -> anyhow::Result<()> {
+    let HandleAppealOptions {
+        custom_id,
+        db_pool,
+        config,
+        bot_id,
+    } = options;
+
+    let is_admin = AdminModelController::get(db_pool, &interaction.user.id)
+        .await?
+        .is_some();
+
+    if interaction.guild_id != Some(config.admins_server_id) || !is_admin {
+        return respond_ephemeral(&cache_http, interaction, "Only admins can resolve appeals.")
+            .await;
+    }
+
+    let Some(open_appeal) =
+        AppealModelController::get_open_by_id(db_pool, custom_id.appeal_id()).await?
+    else {
+        return respond_ephemeral(
+            &cache_http,
+            interaction,
+            "This appeal was already resolved.",
+        )
+        .await;
+    };
+
+    // accepting deactivates and broadcasts the report, which can take longer than discord waits for a response
+    interaction
+        .create_response(&cache_http, CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    // the same lock as the other report changes, so the report cannot change until the appeal is resolved
+    let _guard = locks::lock_user_id(open_appeal.user_id).await;
+
+    let Some(bad_actor) =
+        BadActorModelController::get_by_id(db_pool, open_appeal.bad_actor_id).await?
+    else {
+        anyhow::bail!(
+            "Bad actor entry {} of appeal {} does not exist",
+            open_appeal.bad_actor_id,
+            open_appeal.id
+        );
+    };
+
+    let appellant = open_appeal.user_id.to_user(&cache_http).await?;
+
+    // deactivate first, so a failure leaves the appeal open with its buttons to try again
+    let deactivated = if custom_id.status() == AppealStatus::Accepted && bad_actor.is_active {
+        let deactivate_result = BadActorModelController::deavtivate(
+            db_pool,
+            bad_actor.id,
+            format!("Appeal {} was accepted.", open_appeal.id),
+            interaction.user.id,
+            config.admins_server_id,
+        )
+        .await;
+
+        match deactivate_result {
+            Ok(deactivated) => Some(deactivated),
+            Err(e) => {
+                followup_ephemeral(
+                    &cache_http,
+                    interaction,
+                    "Failed to deactivate the report. The appeal is still open, please try again.",
+                )
+                .await?;
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+
+    let Some(appeal) = AppealModelController::resolve(
+        db_pool,
+        open_appeal.id,
+        custom_id.status(),
+        interaction.user.id,
+    )
+    .await?
+    else {
+        return followup_ephemeral(
+            &cache_http,
+            interaction,
+            "This appeal was already resolved.",
+        )
+        .await;
+    };
+
+    if let Some(deactivated) = &deactivated {
+        let options = BroadcastDeactivationOptions {
+            deactivated,
+            appellant: &appellant,
+            acting_user: &interaction.user,
+            config,
+            db_pool,
+            bot_id,
+        };
+        broadcast_deactivation(&cache_http, options).await;
+    }
+
+    let (colour, content) = match appeal.status {
+        AppealStatus::Accepted => (
+            EmbedColor::Green,
+            format!(
+                "Appeal accepted by {}.",
+                format::fdisplay(&interaction.user)
+            ),
+        ),
+        _ => (
+            EmbedColor::Red,
+            format!(
+                "Appeal rejected by {}.",
+                format::fdisplay(&interaction.user)
+            ),
+        ),
+    };
+
+    let bad_actor = deactivated.as_ref().unwrap_or(&bad_actor);
+
+    let response = EditInteractionResponse::new()
+        .content(content)
+        .embed(get_appeal_embed(&appeal, &appellant, bad_actor, colour))
+        .components(vec![]);
+
+    interaction.edit_response(&cache_http, response).await?;
+
+    notify_appellant(&cache_http, &appeal, &appellant).await;
+
+    Ok(())
+}
+
+async fn broadcast_deactivation(
+    cache_http: impl CacheHttp,
+    options: BroadcastDeactivationOptions<'_>,
+) {
+    let BroadcastDeactivationOptions {
+        deactivated,
+        appellant,
+        acting_user,
+        config,
+        db_pool,
+        bot_id,
+    } = options;
+
+    let origin_guild = config
+        .admins_server_id
+        .to_partial_guild(&cache_http)
+        .await
+        .ok();
+
+    let broadcast_options = BroadcastOptions {
+        config,
+        db_pool,
+        reporting_user: acting_user,
+        reporting_bot_id: bot_id,
+        bad_actor: deactivated,
+        bad_actor_user: appellant,
+        origin_guild,
+        origin_guild_id: config.admins_server_id,
+        broadcast_type: BroadcastType::Deactivate,
+    };
+
+    broadcast_handler::broadcast(&cache_http, broadcast_options).await;
+}
+
+async fn notify_appellant(cache_http: impl CacheHttp, appeal: &Appeal, appellant: &User) {
+    let content = match appeal.status {
+        AppealStatus::Accepted => "Your appeal was accepted. Your report has been deactivated and servers that automatically unban deactivated reports will lift their bans.",
+        _ => "Your appeal was rejected. Your report remains active.",
+    };
+
+    if appellant
+        .direct_message(&cache_http, CreateMessage::new().content(content))
+        .await
+        .is_err()
+    {
+        let log_msg = format!(
+            "Failed to inform {} about the outcome of appeal {} in DM",
+            format::display(appellant),
+            appeal.id
+        );
+        Logger::get().warn(&cache_http, log_msg).await;
+    }
+}
+
+async fn respond_ephemeral(
+    cache_http: impl CacheHttp,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> anyhow::Result<()> {
+    let response = CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true);
+
+    interaction
+        .create_response(&cache_http, CreateInteractionResponse::Message(response))
+        .await?;
+
+    Ok(())
+}
+
+async fn followup_ephemeral(
+    cache_http: impl CacheHttp,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> anyhow::Result<()> {
+    let followup = CreateInteractionResponseFollowup::new()
+        .content(content)
+        .ephemeral(true);
+
+    interaction.create_followup(&cache_http, followup).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_id_round_trips() {
+        for custom_id in [AppealCustomId::Accept(12), AppealCustomId::Reject(7)] {
+            let parsed = AppealCustomId::from_str(&custom_id.to_string()).unwrap();
+            assert_eq!(parsed, custom_id);
+        }
+    }
+
+    #[test]
+    fn rejects_other_custom_ids() {
+        assert!(AppealCustomId::from_str("ban").is_err());
+        assert!(AppealCustomId::from_str("appeal_accept:abc").is_err());
+        assert!(AppealCustomId::from_str("appeal_maybe:3").is_err());
+    }
+}
@@ -1,25 +1,38 @@
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, time::Duration};
 
+use chrono::Utc;
 use futures::TryFutureExt;
 use poise::serenity_prelude as serenity;
 use serenity::{
-    CacheHttp, ComponentInteraction, ComponentInteractionDataKind, CreateMessage, EditMessage,
-    Embed, GuildChannel, GuildId, Member, Message, User, UserId,
+    ButtonStyle, CacheHttp, ComponentInteraction, ComponentInteractionCollector,
+    ComponentInteractionDataKind, CreateActionRow, CreateButton, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, EditInteractionResponse, EditMember, EditMessage, Embed, GuildChannel,
+    GuildId, Member, Message, User, UserId,
 };
 use sqlx::PgPool;
 
+use crate::database::controllers::badactor_model_controller::BadActorModelController;
+use crate::database::controllers::moderationaction_model_controller::{
+    CreateModerationAction, ModerationActionModelController, ModerationActionType, ModerationSource,
+};
+use crate::database::controllers::serverconfig_model_controller::ServerConfigModelController;
 use crate::{
     honeypot::message::get_log_channel,
-    util::{format, logger::Logger},
+    util::{config::Config, format, logger::Logger},
 };
 
+use super::appeal::{handle_appeal_interaction, AppealCustomId, HandleAppealOptions};
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CustomId {
     Ban,
     SoftBan,
     Kick,
+    Timeout,
     Unban,
     Confirm,
+    Reason,
     Cancel,
     NoAction,
 }
@@ -32,8 +45,10 @@ impl FromStr for CustomId {
             "ban" => Ok(Self::Ban),
             "softban" => Ok(Self::SoftBan),
             "kick" => Ok(Self::Kick),
+            "timeout" => Ok(Self::Timeout),
             "unban" => Ok(Self::Unban),
             "confirm" => Ok(Self::Confirm),
+            "reason" => Ok(Self::Reason),
             "cancel" => Ok(Self::Cancel),
             "no_action" => Ok(Self::NoAction),
             _ => anyhow::bail!("Unknown custom id {s}"),
@@ -47,8 +62,10 @@ impl Display for CustomId {
             Self::Ban => write!(f, "ban"),
             Self::SoftBan => write!(f, "softban"),
             Self::Kick => write!(f, "kick"),
+            Self::Timeout => write!(f, "timeout"),
             Self::Unban => write!(f, "unban"),
             Self::Confirm => write!(f, "confirm"),
+            Self::Reason => write!(f, "reason"),
             Self::Cancel => write!(f, "cancel"),
             Self::NoAction => write!(f, "no_action"),
         }
@@ -60,6 +77,7 @@ pub enum ModerationCustomId {
     Ban,
     SoftBan,
     Kick,
+    Timeout,
     Unban,
     NoAction,
 }
@@ -70,12 +88,40 @@ impl Display for ModerationCustomId {
             Self::Ban => write!(f, "ban"),
             Self::SoftBan => write!(f, "softban"),
             Self::Kick => write!(f, "kick"),
+            Self::Timeout => write!(f, "timeout"),
             Self::Unban => write!(f, "unban"),
             Self::NoAction => write!(f, "no_action"),
         }
     }
 }
 
+impl FromStr for ModerationCustomId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ModerationCustomId::try_from(CustomId::from_str(s)?)
+    }
+}
+
+impl ModerationCustomId {
+    /// The moderation action this button takes. [ModerationCustomId::NoAction] does not take any action.
+    fn action_type(&self) -> Option<ModerationActionType> {
+        match self {
+            Self::Ban => Some(ModerationActionType::Ban),
+            Self::SoftBan => Some(ModerationActionType::SoftBan),
+            Self::Kick => Some(ModerationActionType::Kick),
+            Self::Timeout => Some(ModerationActionType::Timeout),
+            Self::Unban => Some(ModerationActionType::Unban),
+            Self::NoAction => None,
+        }
+    }
+
+    /// Destructive actions have to be confirmed before they are taken.
+    fn needs_confirmation(&self) -> bool {
+        matches!(self, Self::Ban | Self::SoftBan | Self::Kick)
+    }
+}
+
 impl TryFrom<CustomId> for ModerationCustomId {
     type Error = anyhow::Error;
 
@@ -84,6 +130,7 @@ impl TryFrom<CustomId> for ModerationCustomId {
             CustomId::Ban => Ok(ModerationCustomId::Ban),
             CustomId::SoftBan => Ok(ModerationCustomId::SoftBan),
             CustomId::Kick => Ok(ModerationCustomId::Kick),
+            CustomId::Timeout => Ok(ModerationCustomId::Timeout),
             CustomId::Unban => Ok(ModerationCustomId::Unban),
             CustomId::NoAction => Ok(ModerationCustomId::NoAction),
             _ => anyhow::bail!("custom id `{custom_id}` is not a custom moderation id."),
@@ -91,14 +138,127 @@ impl TryFrom<CustomId> for ModerationCustomId {
     }
 }
 
+/// The durations a moderator can pick after pressing the timeout button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeoutDuration {
+    OneHour,
+    OneDay,
+    SevenDays,
+    TwentyEightDays,
+}
+
+impl TimeoutDuration {
+    pub const ALL: [Self; 4] = [
+        Self::OneHour,
+        Self::OneDay,
+        Self::SevenDays,
+        Self::TwentyEightDays,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OneHour => "1 Hour",
+            Self::OneDay => "1 Day",
+            Self::SevenDays => "7 Days",
+            Self::TwentyEightDays => "28 Days",
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            Self::OneHour => chrono::Duration::hours(1),
+            Self::OneDay => chrono::Duration::days(1),
+            Self::SevenDays => chrono::Duration::days(7),
+            // the longest timeout discord allows
+            Self::TwentyEightDays => chrono::Duration::days(28),
+        }
+    }
+}
+
+impl FromStr for TimeoutDuration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1h" => Ok(Self::OneHour),
+            "1d" => Ok(Self::OneDay),
+            "7d" => Ok(Self::SevenDays),
+            "28d" => Ok(Self::TwentyEightDays),
+            _ => anyhow::bail!("Unknown timeout duration {s}"),
+        }
+    }
+}
+
+impl Display for TimeoutDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OneHour => write!(f, "1h"),
+            Self::OneDay => write!(f, "1d"),
+            Self::SevenDays => write!(f, "7d"),
+            Self::TwentyEightDays => write!(f, "28d"),
+        }
+    }
+}
+
+/// The custom ID of the duration select menu shown after pressing the timeout button.
+const TIMEOUT_DURATION_ID: &str = "timeout_duration";
+
+/// Versions the layout of [ModerationButtonId], so it can change without breaking posted buttons.
+const MODERATION_BUTTON_VERSION: &str = "mod1";
+
+/// The custom ID of a moderation button on a broadcast message.
+/// It carries everything needed to moderate, so the embed never has to be parsed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModerationButtonId {
+    pub action: ModerationCustomId,
+    pub report_id: i32,
+    pub target_user_id: UserId,
+}
+
+impl FromStr for ModerationButtonId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+
+        let [version, action, report_id, target_user_id] = parts.as_slice() else {
+            anyhow::bail!("Unknown moderation button id {s}");
+        };
+
+        if *version != MODERATION_BUTTON_VERSION {
+            anyhow::bail!("Unknown moderation button id version {version}");
+        }
+
+        Ok(Self {
+            action: ModerationCustomId::from_str(action)?,
+            report_id: report_id.parse::<i32>()?,
+            target_user_id: UserId::from_str(target_user_id)?,
+        })
+    }
+}
+
+impl Display for ModerationButtonId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{MODERATION_BUTTON_VERSION}:{}:{}:{}",
+            self.action, self.report_id, self.target_user_id
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct HandleModerationOptions<'a> {
     interaction_guild_id: GuildId,
     custom_id: ModerationCustomId,
+    report_id: i32,
+    /// The reason the moderator entered when confirming the action.
+    reason: Option<String>,
+    /// How long to time out the user for, only used by [ModerationCustomId::Timeout].
+    timeout_duration: Option<TimeoutDuration>,
     db_pool: &'a PgPool,
     target_user: &'a User,
     interaction_user: &'a User,
-    embed: &'a Embed,
 }
 
 #[derive(Debug)]
@@ -115,6 +275,17 @@ struct CanModerateOptions<'a> {
     interaction_member: &'a Member,
 }
 
+#[derive(Debug)]
+struct RecordActionOptions<'a> {
+    db_pool: &'a PgPool,
+    interaction_guild_id: GuildId,
+    custom_id: ModerationCustomId,
+    report_id: i32,
+    target_user: &'a User,
+    interaction_user: &'a User,
+    result: &'a anyhow::Result<()>,
+}
+
 #[derive(Debug)]
 struct HandleModerationFailOptions<'a> {
     error: anyhow::Error,
@@ -131,16 +302,58 @@ struct HandleModerationSuccessOptions<'a> {
     log_channel: &'a GuildChannel,
     target_user: &'a User,
     interaction_user: &'a User,
+    reason: Option<&'a str>,
+    timeout_duration: Option<TimeoutDuration>,
+}
+
+/// What the moderator chose in the confirmation prompt of a destructive button.
+enum Confirmation {
+    Confirmed { reason: Option<String> },
+    Cancelled,
 }
 
+#[derive(Debug, poise::Modal)]
+#[name = "Moderation Reason"]
+struct ReasonModal {
+    #[name = "Reason"]
+    #[placeholder = "Added to the audit log and the log channel message"]
+    #[paragraph]
+    #[max_length = 400]
+    reason: String,
+}
+
+/// poise expects an owned context for modals, this lends it the borrowed one.
+struct ModalContext<'a>(&'a serenity::Context);
+
+impl AsRef<serenity::Context> for ModalContext<'_> {
+    fn as_ref(&self) -> &serenity::Context {
+        self.0
+    }
+}
+
+/// How long a moderator has to confirm a destructive action or enter a reason.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub async fn handle_component_interaction(
     interaction: &ComponentInteraction,
-    cache_http: impl CacheHttp,
+    ctx: &serenity::Context,
     db_pool: &PgPool,
+    config: &Config,
+    bot_id: UserId,
 ) -> anyhow::Result<()> {
     match interaction.data.kind {
         ComponentInteractionDataKind::Button => {
-            handle_button_interaction(interaction, &cache_http, db_pool).await?;
+            if let Ok(custom_id) = AppealCustomId::from_str(&interaction.data.custom_id) {
+                let options = HandleAppealOptions {
+                    custom_id,
+                    db_pool,
+                    config,
+                    bot_id,
+                };
+                return handle_appeal_interaction(interaction, ctx, options).await;
+            }
+
+            handle_button_interaction(interaction, ctx, db_pool).await?;
         }
         _ => return Ok(()),
     }
@@ -150,27 +363,28 @@ pub async fn handle_component_interaction(
 
 async fn handle_button_interaction(
     interaction: &ComponentInteraction,
-    cache_http: impl CacheHttp,
+    ctx: &serenity::Context,
     db_pool: &PgPool,
 ) -> anyhow::Result<()> {
     let Some(interaction_guild_id) = interaction.guild_id else {
         return Ok(());
     };
 
-    let Ok(custom_id) =
-        ModerationCustomId::try_from(CustomId::from_str(&interaction.data.custom_id)?)
-    else {
-        return Ok(());
+    let button_id = match ModerationButtonId::from_str(&interaction.data.custom_id) {
+        Ok(button_id) => button_id,
+        Err(_) => match parse_legacy_button(interaction) {
+            Some(button_id) => button_id,
+            None => return Ok(()),
+        },
     };
 
-    let Some(embed) = get_broadcast_embed(interaction) else {
-        return Ok(());
-    };
+    let ModerationButtonId {
+        action: custom_id,
+        report_id,
+        target_user_id,
+    } = button_id;
 
-    let Ok(interaction_member) = interaction_guild_id
-        .member(&cache_http, interaction.user.id)
-        .await
-    else {
+    let Ok(interaction_member) = interaction_guild_id.member(ctx, interaction.user.id).await else {
         return Ok(());
     };
 
@@ -179,32 +393,222 @@ async fn handle_button_interaction(
         custom_id,
         interaction_member: &interaction_member,
     };
-    if !can_moderate(&cache_http, options).await {
+    if !can_moderate(ctx, options).await {
         return Ok(());
     }
 
-    let target_user = get_target_user(&cache_http, &embed).await?;
+    let target_user = target_user_id.to_user(ctx).await?;
+
+    let reason = if custom_id.needs_confirmation() {
+        match confirm_moderation(ctx, interaction, custom_id, &target_user).await? {
+            Confirmation::Confirmed { reason } => reason,
+            Confirmation::Cancelled => return Ok(()),
+        }
+    } else {
+        None
+    };
+
+    let timeout_duration = if custom_id == ModerationCustomId::Timeout {
+        match select_timeout_duration(ctx, interaction, &target_user).await? {
+            Some(timeout_duration) => Some(timeout_duration),
+            None => return Ok(()),
+        }
+    } else {
+        None
+    };
 
     let options = HandleModerationOptions {
         interaction_guild_id,
         custom_id,
+        report_id,
+        reason,
+        timeout_duration,
         db_pool,
         target_user: &target_user,
         interaction_user: &interaction_member.user,
-        embed: &embed,
     };
-    handle_moderation(&cache_http, options).await;
+    handle_moderation(ctx, options).await;
 
     let options = RemoveButtonOptions {
         interaction_guild_id,
         target_user: &target_user,
         message: &mut interaction.message.clone(),
     };
-    remove_buttons(&cache_http, options).await;
+    remove_buttons(ctx, options).await;
 
     Ok(())
 }
 
+/// Asks the moderator in an ephemeral message to confirm the action, optionally with a reason.
+async fn confirm_moderation(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    custom_id: ModerationCustomId,
+    target_user: &User,
+) -> anyhow::Result<Confirmation> {
+    let buttons = vec![
+        CreateButton::new(CustomId::Confirm.to_string())
+            .label("Confirm")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(CustomId::Reason.to_string())
+            .label("Confirm with Reason")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(CustomId::Cancel.to_string())
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ];
+
+    let prompt = CreateInteractionResponseMessage::new()
+        .content(format!(
+            "Do you really want to {custom_id} {} from this server?",
+            format::fdisplay(target_user)
+        ))
+        .components(vec![CreateActionRow::Buttons(buttons)])
+        .ephemeral(true);
+
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Message(prompt))
+        .await?;
+    let prompt_message = interaction.get_response(ctx).await?;
+
+    let Some(press) = ComponentInteractionCollector::new(ctx)
+        .message_id(prompt_message.id)
+        .author_id(interaction.user.id)
+        .timeout(CONFIRMATION_TIMEOUT)
+        .await
+    else {
+        close_prompt(ctx, interaction, "Timed out, no action was taken.").await;
+        return Ok(Confirmation::Cancelled);
+    };
+
+    let confirmation = match CustomId::from_str(&press.data.custom_id)? {
+        CustomId::Confirm => {
+            press
+                .create_response(ctx, CreateInteractionResponse::Acknowledge)
+                .await?;
+
+            Confirmation::Confirmed { reason: None }
+        }
+        CustomId::Reason => {
+            let modal = poise::execute_modal_on_component_interaction::<ReasonModal>(
+                ModalContext(ctx),
+                press,
+                None,
+                Some(CONFIRMATION_TIMEOUT),
+            )
+            .await?;
+
+            match modal {
+                Some(modal) => Confirmation::Confirmed {
+                    reason: Some(modal.reason),
+                },
+                None => Confirmation::Cancelled,
+            }
+        }
+        _ => {
+            press
+                .create_response(ctx, CreateInteractionResponse::Acknowledge)
+                .await?;
+
+            Confirmation::Cancelled
+        }
+    };
+
+    let content = match &confirmation {
+        Confirmation::Confirmed { .. } => {
+            format!("Confirmed, taking moderation action `{custom_id}`.")
+        }
+        Confirmation::Cancelled => "Cancelled, no action was taken.".to_string(),
+    };
+    close_prompt(ctx, interaction, content).await;
+
+    Ok(confirmation)
+}
+
+/// Asks the moderator in an ephemeral message how long to time out the user.
+/// Returns `None` if they cancelled or did not pick a duration in time.
+async fn select_timeout_duration(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    target_user: &User,
+) -> anyhow::Result<Option<TimeoutDuration>> {
+    let options = TimeoutDuration::ALL
+        .iter()
+        .map(|d| CreateSelectMenuOption::new(d.label(), d.to_string()))
+        .collect::<Vec<_>>();
+
+    let select_menu = CreateSelectMenu::new(
+        TIMEOUT_DURATION_ID,
+        CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Timeout duration");
+
+    let cancel_button = CreateButton::new(CustomId::Cancel.to_string())
+        .label("Cancel")
+        .style(ButtonStyle::Secondary);
+
+    let prompt = CreateInteractionResponseMessage::new()
+        .content(format!(
+            "How long do you want to time out {}?",
+            format::fdisplay(target_user)
+        ))
+        .components(vec![
+            CreateActionRow::SelectMenu(select_menu),
+            CreateActionRow::Buttons(vec![cancel_button]),
+        ])
+        .ephemeral(true);
+
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Message(prompt))
+        .await?;
+    let prompt_message = interaction.get_response(ctx).await?;
+
+    let Some(press) = ComponentInteractionCollector::new(ctx)
+        .message_id(prompt_message.id)
+        .author_id(interaction.user.id)
+        .timeout(CONFIRMATION_TIMEOUT)
+        .await
+    else {
+        close_prompt(ctx, interaction, "Timed out, no action was taken.").await;
+        return Ok(None);
+    };
+
+    press
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    let timeout_duration = match &press.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values
+            .first()
+            .map(|value| TimeoutDuration::from_str(value))
+            .transpose()?,
+        _ => None,
+    };
+
+    let content = match timeout_duration {
+        Some(d) => format!("Confirmed, timing out for {}.", d.label()),
+        None => "Cancelled, no action was taken.".to_string(),
+    };
+    close_prompt(ctx, interaction, content).await;
+
+    Ok(timeout_duration)
+}
+
+/// Replaces the confirmation prompt with a final message. Failing to do so is only logged.
+async fn close_prompt(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    content: impl Into<String>,
+) {
+    let edit = EditInteractionResponse::new()
+        .content(content)
+        .components(vec![]);
+
+    if let Err(e) = interaction.edit_response(ctx, edit).await {
+        tracing::warn!("Failed to close moderation confirmation prompt: {e}");
+    }
+}
+
 async fn remove_buttons(cache_http: impl CacheHttp, options: RemoveButtonOptions<'_>) {
     let RemoveButtonOptions {
         interaction_guild_id,
@@ -276,6 +680,14 @@ async fn can_moderate(cache_http: impl CacheHttp, options: CanModerateOptions<'_
                 let message = format!("Guild member {} tried to use moderation button `{custom_id}` but lacks kick permissions", format::display(&interaction_member.user));
                 tracing::warn!("{message}");
 
+                return false;
+            }
+        }
+        ModerationCustomId::Timeout => {
+            if !permissions.moderate_members() {
+                let message = format!("Guild member {} tried to use moderation button `{custom_id}` but lacks moderate members permissions", format::display(&interaction_member.user));
+                tracing::warn!("{message}");
+
                 return false;
             }
         }
@@ -284,6 +696,26 @@ async fn can_moderate(cache_http: impl CacheHttp, options: CanModerateOptions<'_
     true
 }
 
+/// Buttons posted before [ModerationButtonId] only had the action as their custom ID,
+/// so the report ID and target user have to be read from the broadcast embed.
+fn parse_legacy_button(interaction: &ComponentInteraction) -> Option<ModerationButtonId> {
+    let action = ModerationCustomId::from_str(&interaction.data.custom_id).ok()?;
+    let embed = get_broadcast_embed(interaction)?;
+
+    let target_user_id = parse_legacy_user_id(embed.title.as_deref()?).ok()?;
+    let report_id = embed
+        .fields
+        .iter()
+        .find(|f| f.name.as_str() == "Report ID")
+        .and_then(|f| f.value.parse::<i32>().ok())?;
+
+    Some(ModerationButtonId {
+        action,
+        report_id,
+        target_user_id,
+    })
+}
+
 fn get_broadcast_embed(interaction: &ComponentInteraction) -> Option<Embed> {
     let embeds = interaction.message.embeds.clone();
 
@@ -312,14 +744,8 @@ fn get_broadcast_embed(interaction: &ComponentInteraction) -> Option<Embed> {
     Some(first_embed)
 }
 
-async fn get_target_user(
-    cache_http: impl CacheHttp,
-    broadcast_embed: &Embed,
-) -> anyhow::Result<User> {
-    let Some(title) = broadcast_embed.title.clone() else {
-        anyhow::bail!("Broadcast embed missing title");
-    };
-
+/// Reads the user ID from a broadcast embed title like ``Name (`123`)``.
+fn parse_legacy_user_id(title: &str) -> anyhow::Result<UserId> {
     let Some(id_start) = title.find('`') else {
         anyhow::bail!(
             "Failed to find first backtick for parsing the userid from broadcast embed title"
@@ -332,39 +758,55 @@ async fn get_target_user(
     };
 
     let id_str = &title[id_start + 1..id_start + 1 + id_end];
-    let user_id = UserId::from_str(id_str)?;
 
-    user_id
-        .to_user(cache_http)
-        .await
-        .map_err(anyhow::Error::from)
+    UserId::from_str(id_str).map_err(anyhow::Error::from)
 }
 
-fn get_ban_reason(embed: &Embed) -> anyhow::Result<String> {
-    let embed_fields = embed.fields.clone();
+/// The ban reason of the report with the moderator's reason appended.
+/// `None` if neither the report exists anymore nor the moderator entered a reason.
+async fn get_ban_reason(
+    db_pool: &PgPool,
+    report_id: i32,
+    moderator_reason: Option<&str>,
+) -> Option<String> {
+    let report_reason = BadActorModelController::get_by_id(db_pool, report_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|bad_actor| bad_actor.ban_reason(None));
 
-    let Some(report_id_field) = embed_fields.iter().find(|f| f.name.as_str() == "Report ID") else {
-        anyhow::bail!("Cannot find field `Report ID` in broadcast embed")
-    };
+    append_reason(report_reason, moderator_reason)
+}
 
-    let Some(type_field) = embed_fields.iter().find(|f| f.name.as_str() == "Type") else {
-        anyhow::bail!("Cannot find field `Type` in broadcast embed")
-    };
+/// Days of messages the guild wants deleted on ban, Discord's maximum of 7 if its config cannot be loaded.
+async fn get_ban_delete_message_days(db_pool: &PgPool, guild_id: GuildId) -> u8 {
+    ServerConfigModelController::get_by_guild_id(db_pool, guild_id)
+        .await
+        .ok()
+        .flatten()
+        .map_or(7, |config| config.ban_delete_message_days)
+}
 
-    Ok(format!(
-        "Bad Actor {} ({})",
-        type_field.value, report_id_field.value
-    ))
+fn append_reason(report_reason: Option<String>, moderator_reason: Option<&str>) -> Option<String> {
+    match (report_reason, moderator_reason) {
+        (Some(report_reason), Some(moderator_reason)) => {
+            Some(format!("{report_reason}: {moderator_reason}"))
+        }
+        (report_reason, None) => report_reason,
+        (None, moderator_reason) => moderator_reason.map(String::from),
+    }
 }
 
 pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModerationOptions<'_>) {
     let HandleModerationOptions {
         interaction_guild_id,
         custom_id,
+        report_id,
+        reason,
+        timeout_duration,
         db_pool,
         target_user,
         interaction_user,
-        embed,
     } = options;
 
     if custom_id == ModerationCustomId::NoAction {
@@ -387,19 +829,26 @@ pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModera
         return;
     };
 
+    let delete_message_days = get_ban_delete_message_days(db_pool, interaction_guild_id).await;
+
     let mut moderation_error = None;
 
     match custom_id {
         ModerationCustomId::Ban => {
-            if let Ok(ban_reason) = get_ban_reason(embed) {
+            if let Some(ban_reason) = get_ban_reason(db_pool, report_id, reason.as_deref()).await {
                 if let Err(e) = interaction_guild_id
-                    .ban_with_reason(&cache_http.http(), target_user.id, 7, ban_reason)
+                    .ban_with_reason(
+                        &cache_http.http(),
+                        target_user.id,
+                        delete_message_days,
+                        ban_reason,
+                    )
                     .await
                 {
                     moderation_error = Some(anyhow::Error::from(e));
                 }
             } else if let Err(e) = interaction_guild_id
-                .ban(&cache_http.http(), target_user.id, 7)
+                .ban(&cache_http.http(), target_user.id, delete_message_days)
                 .await
             {
                 moderation_error = Some(anyhow::Error::from(e));
@@ -407,19 +856,57 @@ pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModera
         }
         ModerationCustomId::SoftBan => {
             let http = cache_http.http();
-
-            let softban_res = interaction_guild_id
-                .ban(http, target_user.id, 7)
-                .and_then(|_| async move { interaction_guild_id.unban(http, target_user.id).await })
-                .await;
+            let ban_reason = get_ban_reason(db_pool, report_id, reason.as_deref()).await;
+
+            let softban_res = async {
+                match ban_reason {
+                    Some(ban_reason) => {
+                        interaction_guild_id
+                            .ban_with_reason(http, target_user.id, delete_message_days, ban_reason)
+                            .await
+                    }
+                    None => {
+                        interaction_guild_id
+                            .ban(http, target_user.id, delete_message_days)
+                            .await
+                    }
+                }
+            }
+            .and_then(|_| async move { interaction_guild_id.unban(http, target_user.id).await })
+            .await;
 
             if let Err(e) = softban_res {
                 moderation_error = Some(anyhow::Error::from(e));
             }
         }
         ModerationCustomId::Kick => {
+            let kick_res = match reason.as_deref() {
+                Some(reason) => {
+                    interaction_guild_id
+                        .kick_with_reason(&cache_http.http(), target_user.id, reason)
+                        .await
+                }
+                None => {
+                    interaction_guild_id
+                        .kick(&cache_http.http(), target_user.id)
+                        .await
+                }
+            };
+
+            if let Err(e) = kick_res {
+                moderation_error = Some(anyhow::Error::from(e));
+            }
+        }
+        ModerationCustomId::Timeout => {
+            let timeout_duration = timeout_duration.unwrap_or(TimeoutDuration::OneDay);
+            let until = Utc::now() + timeout_duration.duration();
+
             if let Err(e) = interaction_guild_id
-                .kick(&cache_http.http(), target_user.id)
+                .edit_member(
+                    &cache_http,
+                    target_user.id,
+                    EditMember::new().disable_communication_until_datetime(until.into()),
+                )
                 .await
             {
                 moderation_error = Some(anyhow::Error::from(e));
@@ -431,6 +918,17 @@ pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModera
                 .await
             {
                 if e.to_string() == "Unknown Ban" {
+                    let options = RecordActionOptions {
+                        db_pool,
+                        interaction_guild_id,
+                        custom_id,
+                        report_id,
+                        target_user,
+                        interaction_user,
+                        result: &Err(anyhow::Error::from(e)),
+                    };
+                    record_action(&cache_http, options).await;
+
                     return handle_unknown_ban(
                         &cache_http,
                         interaction_guild_id,
@@ -449,7 +947,20 @@ pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModera
         }
     }
 
-    if let Some(e) = moderation_error {
+    let result = moderation_error.map_or(Ok(()), Err);
+
+    let options = RecordActionOptions {
+        db_pool,
+        interaction_guild_id,
+        custom_id,
+        report_id,
+        target_user,
+        interaction_user,
+        result: &result,
+    };
+    record_action(&cache_http, options).await;
+
+    if let Err(e) = result {
         let options = HandleModerationFailOptions {
             custom_id,
             interaction_guild_id,
@@ -465,11 +976,49 @@ pub async fn handle_moderation(cache_http: impl CacheHttp, options: HandleModera
             target_user,
             interaction_user,
             log_channel: &log_channel,
+            reason: reason.as_deref(),
+            timeout_duration,
         };
         handle_moderation_success(&cache_http, options).await;
     }
 }
 
+/// Records the moderation action taken with a broadcast embed button in the `moderation_actions` table.
+/// Failing to record it is only logged, the moderation action itself already happened.
+async fn record_action(cache_http: impl CacheHttp, options: RecordActionOptions<'_>) {
+    let RecordActionOptions {
+        db_pool,
+        interaction_guild_id,
+        custom_id,
+        report_id,
+        target_user,
+        interaction_user,
+        result,
+    } = options;
+
+    let Some(action) = custom_id.action_type() else {
+        return;
+    };
+
+    let create_action = CreateModerationAction {
+        guild_id: interaction_guild_id,
+        target_user_id: target_user.id,
+        bad_actor_id: Some(report_id),
+        action,
+        source: ModerationSource::Button,
+        acting_user_id: Some(interaction_user.id),
+        result,
+    };
+
+    if let Err(e) = ModerationActionModelController::create(db_pool, create_action).await {
+        let log_msg = format!(
+            "Failed to record {action} of {} in guild {interaction_guild_id} using the broadcast embed buttons",
+            format::display(target_user)
+        );
+        Logger::get().error(&cache_http, e, log_msg).await;
+    }
+}
+
 async fn handle_moderation_fail(
     cache_http: impl CacheHttp,
     options: HandleModerationFailOptions<'_>,
@@ -520,13 +1069,21 @@ async fn handle_moderation_success(
         log_channel,
         target_user,
         interaction_user,
+        reason,
+        timeout_duration,
     } = options;
 
-    let guild_message = format!(
+    let mut guild_message = format!(
         "{} took moderation action `{custom_id}` against user {} using the broadcast embed buttons.",
         format::fdisplay(interaction_user),
         format::fdisplay(target_user)
     );
+    if let Some(timeout_duration) = timeout_duration {
+        guild_message.push_str(&format!("\nDuration: {}", timeout_duration.label()));
+    }
+    if let Some(reason) = reason {
+        guild_message.push_str(&format!("\nReason: {reason}"));
+    }
 
     if let Err(e) = log_channel
         .send_message(&cache_http, CreateMessage::default().content(guild_message))
@@ -571,3 +1128,76 @@ async fn handle_unknown_ban(
         Logger::get().error(&cache_http, e, log_msg).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moderation_button_id_round_trips() {
+        let button_id = ModerationButtonId {
+            action: ModerationCustomId::SoftBan,
+            report_id: 42,
+            target_user_id: UserId::new(123456789012345678),
+        };
+
+        let custom_id = button_id.to_string();
+        assert_eq!(custom_id, "mod1:softban:42:123456789012345678");
+        assert_eq!(ModerationButtonId::from_str(&custom_id).unwrap(), button_id);
+    }
+
+    #[test]
+    fn rejects_legacy_and_unknown_button_ids() {
+        assert!(ModerationButtonId::from_str("ban").is_err());
+        assert!(ModerationButtonId::from_str("mod2:ban:42:1").is_err());
+        assert!(ModerationButtonId::from_str("mod1:confirm:42:1").is_err());
+        assert!(ModerationButtonId::from_str("appeal_accept:3").is_err());
+    }
+
+    #[test]
+    fn parses_user_id_from_legacy_title() {
+        let user_id = parse_legacy_user_id("Some Spammer (`123456789012345678`)").unwrap();
+        assert_eq!(user_id, UserId::new(123456789012345678));
+
+        assert!(parse_legacy_user_id("Unknown User").is_err());
+    }
+
+    #[test]
+    fn appends_moderator_reason_to_ban_reason() {
+        let report_reason = Some("Spam: phishing links".to_string());
+
+        assert_eq!(
+            append_reason(report_reason.clone(), Some("repeat offender")).as_deref(),
+            Some("Spam: phishing links: repeat offender")
+        );
+        assert_eq!(append_reason(report_reason.clone(), None), report_reason);
+        assert_eq!(
+            append_reason(None, Some("repeat offender")).as_deref(),
+            Some("repeat offender")
+        );
+        assert_eq!(append_reason(None, None), None);
+    }
+
+    #[test]
+    fn only_destructive_actions_need_confirmation() {
+        assert!(ModerationCustomId::Ban.needs_confirmation());
+        assert!(ModerationCustomId::SoftBan.needs_confirmation());
+        assert!(ModerationCustomId::Kick.needs_confirmation());
+        assert!(!ModerationCustomId::Unban.needs_confirmation());
+        assert!(!ModerationCustomId::NoAction.needs_confirmation());
+    }
+
+    #[test]
+    fn timeout_durations_round_trip() {
+        for timeout_duration in TimeoutDuration::ALL {
+            let value = timeout_duration.to_string();
+            assert_eq!(TimeoutDuration::from_str(&value).unwrap(), timeout_duration);
+        }
+
+        assert_eq!(
+            TimeoutDuration::TwentyEightDays.duration(),
+            chrono::Duration::days(28)
+        );
+        assert!(TimeoutDuration::from_str("29d").is_err());
+    }
+}
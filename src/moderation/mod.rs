@@ -1 +1,2 @@
+pub mod appeal;
 pub mod interaction;
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::broadcast::{expiry, outbox, webhooks};
+use crate::honeypot::channels::{populate_honeypot_channels, HoneypotChannels};
+use crate::honeypot::heuristics::{populate_honeypot_heuristics, HoneypotHeuristicsCache};
+use crate::util::config::Config;
+use crate::util::screenshot::{FileManager, GcMode};
+
+use super::runner::{Job, JobContext};
+
+/// Attempts the broadcast deliveries in the outbox that are due.
+pub struct OutboxJob;
+
+#[async_trait::async_trait]
+impl Job for OutboxJob {
+    fn name(&self) -> &'static str {
+        "broadcast_outbox"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()> {
+        outbox::process_due(&job_ctx.ctx, &job_ctx.db_pool).await
+    }
+}
+
+/// Deactivates and broadcasts reports whose expiry has passed.
+pub struct ReportExpiryJob {
+    pub config: Config,
+}
+
+#[async_trait::async_trait]
+impl Job for ReportExpiryJob {
+    fn name(&self) -> &'static str {
+        "report_expiry"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()> {
+        expiry::expire_due(&job_ctx.ctx, &job_ctx.db_pool, &self.config).await
+    }
+}
+
+/// Reloads the honeypot channels and heuristics, so they never drift from the database.
+pub struct HoneypotCacheJob {
+    pub honeypot_channels: HoneypotChannels,
+    pub honeypot_heuristics: HoneypotHeuristicsCache,
+}
+
+#[async_trait::async_trait]
+impl Job for HoneypotCacheJob {
+    fn name(&self) -> &'static str {
+        "honeypot_cache"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()> {
+        populate_honeypot_channels(&self.honeypot_channels, &job_ctx.db_pool).await?;
+        populate_honeypot_heuristics(&self.honeypot_heuristics, &job_ctx.db_pool).await
+    }
+}
+
+/// Removes webhooks that were deleted on Discord, so broadcasts stop retrying them.
+pub struct StaleWebhookJob;
+
+#[async_trait::async_trait]
+impl Job for StaleWebhookJob {
+    fn name(&self) -> &'static str {
+        "stale_webhooks"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()> {
+        webhooks::remove_stale_webhooks(&job_ctx.ctx, &job_ctx.db_pool).await
+    }
+}
+
+/// Quarantines screenshots and evidence no report uses, so they can still be restored by hand.
+pub struct FileGcJob;
+
+/// Longer than the manual default, since nobody looks at what a scheduled run touches.
+const FILE_GC_GRACE_PERIOD: chrono::Duration = chrono::Duration::days(7);
+
+#[async_trait::async_trait]
+impl Job for FileGcJob {
+    fn name(&self) -> &'static str {
+        "file_gc"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(24 * 3600)
+    }
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()> {
+        // a storage shared with other data is only ever cleaned up manually
+        if !FileManager::can_clean_up() {
+            return Ok(());
+        }
+
+        let cutoff = Utc::now() - FILE_GC_GRACE_PERIOD;
+        let summary =
+            FileManager::collect_garbage(&job_ctx.db_pool, cutoff, GcMode::Quarantine).await?;
+
+        if !summary.orphaned.is_empty() {
+            tracing::info!(
+                "Quarantined {} unused files: {}",
+                summary.orphaned.len(),
+                summary.orphaned.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use poise::serenity_prelude as serenity;
+use sqlx::PgPool;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::util::logger::Logger;
+
+/// What every job gets to work with when it runs.
+pub struct JobContext {
+    pub ctx: serenity::Context,
+    pub db_pool: PgPool,
+}
+
+/// A recurring background job.
+#[async_trait::async_trait]
+pub trait Job: Send + Sync {
+    /// The unique name admins use to refer to the job.
+    fn name(&self) -> &'static str;
+
+    /// How long to wait between two scheduled runs.
+    fn interval(&self) -> Duration;
+
+    async fn run(&self, job_ctx: &JobContext) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct JobStatus {
+    pub last_started: Option<DateTime<Utc>>,
+    pub last_finished: Option<DateTime<Utc>>,
+    /// The error of the last run. `None` if it succeeded or the job never ran.
+    pub last_error: Option<String>,
+    pub runs: u64,
+    pub failures: u64,
+}
+
+impl JobStatus {
+    fn start(&mut self) {
+        self.last_started = Some(Utc::now());
+    }
+
+    fn finish(&mut self, result: &anyhow::Result<()>) {
+        self.last_finished = Some(Utc::now());
+        self.runs += 1;
+
+        match result {
+            Ok(()) => self.last_error = None,
+            Err(e) => {
+                self.last_error = Some(format!("{e:#}"));
+                self.failures += 1;
+            }
+        }
+    }
+}
+
+/// A job as reported by [Scheduler::jobs].
+pub struct JobInfo {
+    pub name: &'static str,
+    pub interval: Duration,
+    pub running: bool,
+    pub status: JobStatus,
+}
+
+struct ScheduledJob {
+    job: Box<dyn Job>,
+    /// Held for as long as the job runs, so runs never overlap.
+    running: Arc<AsyncMutex<()>>,
+    status: Mutex<JobStatus>,
+}
+
+impl ScheduledJob {
+    fn new(job: Box<dyn Job>) -> Self {
+        Self {
+            job,
+            running: Arc::new(AsyncMutex::new(())),
+            status: Mutex::new(JobStatus::default()),
+        }
+    }
+
+    /// Returns `None` if the job is already running.
+    fn try_start(&self) -> Option<OwnedMutexGuard<()>> {
+        self.running.clone().try_lock_owned().ok()
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.try_lock().is_err()
+    }
+
+    fn status(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Runs the job while holding the guard from [ScheduledJob::try_start] and reports failures.
+    /// A panicking run counts as a failure, so the job keeps getting scheduled.
+    async fn run(&self, _guard: OwnedMutexGuard<()>, job_ctx: &JobContext) {
+        self.status.lock().unwrap().start();
+
+        let result = catch_panic(self.job.run(job_ctx)).await;
+
+        self.status.lock().unwrap().finish(&result);
+
+        if let Err(e) = result {
+            let log_msg = format!("Scheduled job `{}` failed", self.job.name());
+            Logger::get().error(&job_ctx.ctx, e, log_msg).await;
+        }
+    }
+}
+
+/// Turns a panic of the future into an error.
+async fn catch_panic(future: impl Future<Output = anyhow::Result<()>>) -> anyhow::Result<()> {
+    AssertUnwindSafe(future)
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|panic| anyhow::bail!("The job panicked: {}", panic_message(&*panic)))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs named jobs at their intervals in the background.
+pub struct Scheduler {
+    jobs: Vec<Arc<ScheduledJob>>,
+    job_ctx: Arc<JobContext>,
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.jobs.iter().map(|j| j.job.name()).collect::<Vec<_>>();
+        f.debug_struct("Scheduler").field("jobs", &names).finish()
+    }
+}
+
+impl Scheduler {
+    pub fn new(job_ctx: JobContext, jobs: Vec<Box<dyn Job>>) -> Self {
+        Self {
+            jobs: jobs
+                .into_iter()
+                .map(|job| Arc::new(ScheduledJob::new(job)))
+                .collect(),
+            job_ctx: Arc::new(job_ctx),
+        }
+    }
+
+    /// Spawns a task for every job that runs it at its interval, starting right away.
+    /// A run is skipped if the previous one, or a manually triggered one, is still going.
+    pub fn start(&self) {
+        for job in &self.jobs {
+            let job = job.clone();
+            let job_ctx = self.job_ctx.clone();
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(job.job.interval());
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+                loop {
+                    interval.tick().await;
+
+                    let Some(guard) = job.try_start() else {
+                        tracing::warn!(
+                            "Skipping scheduled job `{}` because it is still running.",
+                            job.job.name()
+                        );
+                        continue;
+                    };
+
+                    job.run(guard, &job_ctx).await;
+                }
+            });
+        }
+    }
+
+    /// Runs a job right away in the background.
+    /// Returns `false` if the job is already running and errors if there is no job with that name.
+    pub fn trigger(&self, name: &str) -> anyhow::Result<bool> {
+        let Some(job) = self.jobs.iter().find(|j| j.job.name() == name) else {
+            anyhow::bail!("There is no job called `{name}`");
+        };
+
+        let Some(guard) = job.try_start() else {
+            return Ok(false);
+        };
+
+        let job = job.clone();
+        let job_ctx = self.job_ctx.clone();
+
+        tokio::spawn(async move { job.run(guard, &job_ctx).await });
+
+        Ok(true)
+    }
+
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        self.jobs
+            .iter()
+            .map(|j| JobInfo {
+                name: j.job.name(),
+                interval: j.job.interval(),
+                running: j.is_running(),
+                status: j.status(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopJob;
+
+    #[async_trait::async_trait]
+    impl Job for NoopJob {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_secs(60)
+        }
+
+        async fn run(&self, _job_ctx: &JobContext) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runs_do_not_overlap() {
+        let job = ScheduledJob::new(Box::new(NoopJob));
+
+        let guard = job.try_start();
+        assert!(guard.is_some());
+        assert!(job.is_running());
+        assert!(job.try_start().is_none());
+
+        drop(guard);
+        assert!(!job.is_running());
+        assert!(job.try_start().is_some());
+    }
+
+    #[test]
+    fn status_keeps_last_error_until_success() {
+        let mut status = JobStatus::default();
+
+        status.start();
+        status.finish(&Err(anyhow::anyhow!("database unavailable")));
+        assert_eq!(status.last_error.as_deref(), Some("database unavailable"));
+        assert_eq!((status.runs, status.failures), (1, 1));
+
+        status.start();
+        status.finish(&Ok(()));
+        assert!(status.last_error.is_none());
+        assert_eq!((status.runs, status.failures), (2, 1));
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        let result = futures::executor::block_on(catch_panic(async {
+            panic!("index out of bounds");
+        }));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The job panicked: index out of bounds"
+        );
+
+        let result = futures::executor::block_on(catch_panic(async {
+            let id = 7;
+            panic!("no job with id {id}");
+        }));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The job panicked: no job with id 7"
+        );
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::Context;
 use poise::serenity_prelude as serenity;
 use serde::Deserialize;
 use serenity::{ChannelId, GuildId};
+use url::Url;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
@@ -10,6 +11,54 @@ pub struct Config {
     pub admins_server_id: GuildId,
     pub admin_server_log_channel: ChannelId,
     pub admin_server_error_log_channel: ChannelId,
+    /// How many distinct servers the same message has to be posted in to be reported as spam.
+    #[serde(default = "default_cross_server_min_guilds")]
+    pub cross_server_min_guilds: usize,
+    /// Where screenshot proof is stored. Defaults to the `screenshots` directory.
+    #[serde(default)]
+    pub screenshot_storage: ScreenshotStorageConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScreenshotStorageConfig {
+    Filesystem {
+        #[serde(default = "default_screenshot_directory")]
+        directory: String,
+    },
+    S3(S3Config),
+}
+
+impl Default for ScreenshotStorageConfig {
+    fn default() -> Self {
+        Self::Filesystem {
+            directory: default_screenshot_directory(),
+        }
+    }
+}
+
+/// An S3-compatible bucket, e.g. on AWS, Cloudflare R2 or MinIO.
+#[derive(Deserialize, Debug, Clone)]
+pub struct S3Config {
+    pub endpoint: Url,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Put the bucket into the path instead of the host name, which MinIO needs.
+    #[serde(default)]
+    pub path_style: bool,
+    /// Prepended to the name of every screenshot, e.g. `screenshots/`.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_cross_server_min_guilds() -> usize {
+    3
+}
+
+fn default_screenshot_directory() -> String {
+    "screenshots".to_string()
 }
 
 impl Config {
@@ -17,6 +66,16 @@ impl Config {
         let file = std::fs::File::open("config.json")?;
         let reader = std::io::BufReader::new(file);
 
-        serde_json::from_reader(reader).context("Failed to parse config file")
+        let config: Self =
+            serde_json::from_reader(reader).context("Failed to parse config file")?;
+
+        if config.cross_server_min_guilds < 2 {
+            anyhow::bail!(
+                "cross_server_min_guilds has to be at least 2, got {}",
+                config.cross_server_min_guilds
+            );
+        }
+
+        Ok(config)
     }
 }
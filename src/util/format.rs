@@ -18,11 +18,11 @@ pub enum TimestampStyle {
 
 pub trait HasNameAndID {
     fn name(&self) -> &str;
-    fn id(&self) -> Cow<str>;
+    fn id(&self) -> Cow<'_, str>;
 }
 
 impl HasNameAndID for User {
-    fn id(&self) -> Cow<str> {
+    fn id(&self) -> Cow<'_, str> {
         self.id.to_string().into()
     }
 
@@ -32,7 +32,7 @@ impl HasNameAndID for User {
 }
 
 impl HasNameAndID for PartialGuild {
-    fn id(&self) -> Cow<str> {
+    fn id(&self) -> Cow<'_, str> {
         self.id.to_string().into()
     }
 
@@ -102,6 +102,17 @@ pub fn display_bool(boolean: bool) -> &'static str {
     }
 }
 
+/// Shortens the input to at most `max_chars` characters, marking the cut with an ellipsis.
+pub fn truncate(input: &str, max_chars: usize) -> String {
+    if input.chars().count() <= max_chars {
+        return input.to_string();
+    }
+
+    let mut output = input.chars().take(max_chars - 1).collect::<String>();
+    output.push('…');
+    output
+}
+
 /// Tries to display the User's global name and gets the username if they don't have one.
 pub fn display_username(user: &User) -> &str {
     user.global_name.as_ref().unwrap_or(&user.name)
@@ -7,4 +7,5 @@ pub mod locks;
 pub mod logger;
 pub mod macros;
 pub mod parsing;
+pub mod s3;
 pub mod screenshot;
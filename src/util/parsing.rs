@@ -1,4 +1,5 @@
 use std::num::NonZeroU64;
+use std::str::FromStr;
 
 use poise::serenity_prelude as serenity;
 use serenity::{GuildId, RoleId};
@@ -31,3 +32,73 @@ pub fn parse_role_ids(str: &str) -> anyhow::Result<Vec<RoleId>> {
 
     Ok(ids)
 }
+
+/// Parses a comma separated list of type names like `spam, bigotry`. `all` parses to an empty list.
+pub fn parse_types<T>(str: &str) -> anyhow::Result<Vec<T>>
+where
+    T: FromStr<Err = anyhow::Error>,
+{
+    if str.trim().eq_ignore_ascii_case("all") {
+        return Ok(Vec::new());
+    }
+
+    str.split(',')
+        .map(|t| T::from_str(&t.trim().to_lowercase()))
+        .collect::<anyhow::Result<Vec<_>>>()
+}
+
+/// Parses a duration like `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(str: &str) -> anyhow::Result<chrono::Duration> {
+    let str = str.trim().to_lowercase();
+
+    let Some(unit) = str.chars().last() else {
+        anyhow::bail!("Duration cannot be empty");
+    };
+
+    let amount = str[..str.len() - unit.len_utf8()].trim().parse::<u32>()?;
+
+    if amount == 0 {
+        anyhow::bail!("Duration cannot be zero");
+    }
+
+    let amount = i64::from(amount);
+
+    let duration = match unit {
+        'm' => chrono::Duration::try_minutes(amount),
+        'h' => chrono::Duration::try_hours(amount),
+        'd' => chrono::Duration::try_days(amount),
+        'w' => chrono::Duration::try_weeks(amount),
+        _ => anyhow::bail!("Invalid duration unit: {}", unit),
+    };
+
+    duration.ok_or(anyhow::Error::msg("Duration is too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(
+            parse_duration("30m").unwrap(),
+            chrono::Duration::minutes(30)
+        );
+        assert_eq!(
+            parse_duration(" 12H ").unwrap(),
+            chrono::Duration::hours(12)
+        );
+        assert_eq!(parse_duration("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), chrono::Duration::weeks(2));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for invalid in ["", "d", "0d", "-1d", "7", "7y", "1.5h"] {
+            assert!(
+                parse_duration(invalid).is_err(),
+                "{invalid} should not parse"
+            );
+        }
+    }
+}
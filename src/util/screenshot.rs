@@ -1,39 +1,410 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
 use anyhow::Context;
-use chrono::{Datelike, Utc};
+use chrono::{DateTime, Utc};
 use poise::serenity_prelude as serenity;
-use serenity::{Attachment, CreateAttachment, UserId};
-use tokio::{
-    fs::{remove_file, File},
-    io::AsyncWriteExt,
+use serenity::{Attachment, CreateAttachment};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::database::controllers::storedfile_model_controller::{
+    StoredFile, StoredFileModelController,
 };
 
+use super::config::ScreenshotStorageConfig;
+use super::s3::S3Storage;
+
+/// Somewhere screenshot proof can be stored under a file name.
+#[async_trait::async_trait]
+pub trait ScreenshotStorage: Send + Sync {
+    async fn put(&self, name: &str, content: Vec<u8>) -> anyhow::Result<()>;
+    async fn get(&self, name: &str) -> anyhow::Result<Vec<u8>>;
+    async fn delete(&self, name: &str) -> anyhow::Result<()>;
+    /// All stored screenshots.
+    async fn list(&self) -> anyhow::Result<Vec<StoredObject>>;
+
+    /// Whether everything [ScreenshotStorage::list] returns belongs to Janitor,
+    /// so unreferenced objects may be quarantined or deleted.
+    fn is_dedicated(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub name: String,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Stores screenshots as files in a directory on the local disk.
+pub struct FilesystemStorage {
+    directory: PathBuf,
+}
+
+impl FilesystemStorage {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ScreenshotStorage for FilesystemStorage {
+    async fn put(&self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.directory.join(name);
+
+        // names may contain a directory, e.g. for quarantined screenshots
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(path, content).await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        tokio::fs::read(self.directory.join(name))
+            .await
+            .with_context(|| format!("Failed to read screenshot {name} from the file system"))
+    }
+
+    async fn delete(&self, name: &str) -> anyhow::Result<()> {
+        tokio::fs::remove_file(self.directory.join(name))
+            .await
+            .with_context(|| format!("Failed to delete screenshot {name} from the file system"))
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<StoredObject>> {
+        let mut entries = match tokio::fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut objects = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+
+            if metadata.is_file() {
+                objects.push(StoredObject {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    modified_at: metadata.modified()?.into(),
+                });
+            }
+        }
+
+        Ok(objects)
+    }
+}
+
+async fn list_names(storage: &dyn ScreenshotStorage) -> anyhow::Result<HashSet<String>> {
+    let objects = storage.list().await?;
+
+    Ok(objects.into_iter().map(|o| o.name).collect())
+}
+
+pub fn storage_from_config(config: &ScreenshotStorageConfig) -> Box<dyn ScreenshotStorage> {
+    match config {
+        ScreenshotStorageConfig::Filesystem { directory } => {
+            Box::new(FilesystemStorage::new(directory))
+        }
+        ScreenshotStorageConfig::S3(s3_config) => Box::new(S3Storage::new(s3_config.clone())),
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CopySummary {
+    pub copied: usize,
+    pub skipped: usize,
+}
+
+/// Copies every screenshot that the destination does not have yet.
+pub async fn copy_all(
+    from: &dyn ScreenshotStorage,
+    to: &dyn ScreenshotStorage,
+) -> anyhow::Result<CopySummary> {
+    let existing = list_names(to).await?;
+    let mut summary = CopySummary::default();
+
+    for StoredObject { name, .. } in from.list().await? {
+        if existing.contains(&name) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let content = from.get(&name).await?;
+        to.put(&name, content)
+            .await
+            .with_context(|| format!("Failed to copy screenshot {name}"))?;
+
+        tracing::info!("Copied screenshot {name}.");
+        summary.copied += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Extensions of stored files that can be shown as images, including names from before content hashes.
+const IMAGE_EXTENSIONS: &[&str] = &["jpeg", "jpg", "png"];
+const MAX_FILE_SIZE: u32 = 5_000_000;
+
+/// The kinds of files that can be uploaded, told apart by their content rather than their name.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FileType {
+    Png,
+    Jpeg,
+    Text,
+}
+
+impl FileType {
+    /// Detects the file type from its magic bytes. Text has to be valid UTF-8 without NUL bytes.
+    pub fn sniff(content: &[u8]) -> Option<Self> {
+        const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+        const JPEG_MAGIC: &[u8] = &[0xFF, 0xD8, 0xFF];
+
+        if content.starts_with(PNG_MAGIC) {
+            Some(Self::Png)
+        } else if content.starts_with(JPEG_MAGIC) {
+            Some(Self::Jpeg)
+        } else if !content.is_empty()
+            && !content.contains(&0)
+            && std::str::from_utf8(content).is_ok()
+        {
+            Some(Self::Text)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Text => "text/plain",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Text => "txt",
+        }
+    }
+}
+
+/// Hex encoded SHA-256 of a file's content.
+pub fn sha256_hex(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Checks an upload and names it after the hash of its content, so identical uploads share a file.
+fn to_stored_file(content: &[u8], allowed: &[FileType]) -> anyhow::Result<StoredFile> {
+    let Some(file_type) = FileType::sniff(content) else {
+        anyhow::bail!("The file is not a supported image or text file");
+    };
+
+    if !allowed.contains(&file_type) {
+        let expected = allowed
+            .iter()
+            .map(|t| format!("`{}`", t.mime_type()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow::bail!(
+            "Expected one of the file types {expected} but got `{}`",
+            file_type.mime_type()
+        );
+    }
+
+    let sha256 = sha256_hex(content);
+
+    Ok(StoredFile {
+        file_name: format!("{sha256}.{}", file_type.extension()),
+        sha256,
+        mime_type: file_type.mime_type().to_string(),
+        size: i32::try_from(content.len())?,
+    })
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifySummary {
+    pub ok: usize,
+    pub missing: Vec<String>,
+    /// Files whose content does not match the recorded hash.
+    pub corrupted: Vec<String>,
+    /// Stored files without a recorded hash, e.g. screenshots from before hashes were recorded.
+    pub unrecorded: Vec<String>,
+}
+
+/// Compares the content of every recorded file with its recorded hash
+/// and lists the stored files that have no record to compare against.
+pub async fn verify_all(
+    storage: &dyn ScreenshotStorage,
+    files: &[StoredFile],
+) -> anyhow::Result<VerifySummary> {
+    let existing = list_names(storage).await?;
+    let mut summary = VerifySummary::default();
+
+    for file in files {
+        if !existing.contains(&file.file_name) {
+            summary.missing.push(file.file_name.clone());
+            continue;
+        }
+
+        let content = match storage.get(&file.file_name).await {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!("Failed to read {} for verification: {e:#}", file.file_name);
+                summary.missing.push(file.file_name.clone());
+                continue;
+            }
+        };
+
+        if sha256_hex(&content) == file.sha256 {
+            summary.ok += 1;
+        } else {
+            summary.corrupted.push(file.file_name.clone());
+        }
+    }
+
+    let recorded = files
+        .iter()
+        .map(|file| file.file_name.as_str())
+        .collect::<HashSet<_>>();
+
+    summary.unrecorded = existing
+        .iter()
+        .filter(|name| !name.starts_with(QUARANTINE_PREFIX) && !recorded.contains(name.as_str()))
+        .cloned()
+        .collect();
+    summary.unrecorded.sort();
+
+    Ok(summary)
+}
+
+/// Quarantined screenshots are moved below this name and ignored by the garbage collection.
+const QUARANTINE_PREFIX: &str = "quarantine/";
+
+/// What the garbage collection does with screenshots no report uses.
+#[derive(Debug, Copy, Clone, PartialEq, poise::ChoiceParameter)]
+pub enum GcMode {
+    #[name = "Dry Run"]
+    DryRun,
+    Quarantine,
+    Delete,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GcSummary {
+    /// Unused files older than the grace period. They are quarantined or deleted unless it is a dry run.
+    pub orphaned: Vec<String>,
+    /// Unused files within the grace period, which might belong to a report that is being created.
+    pub recent: usize,
+    pub referenced: usize,
+}
+
+/// Quarantines or deletes every screenshot that is not referenced and was last modified before the cutoff.
+pub async fn collect_garbage(
+    storage: &dyn ScreenshotStorage,
+    referenced: &HashSet<String>,
+    cutoff: DateTime<Utc>,
+    mode: GcMode,
+) -> anyhow::Result<GcSummary> {
+    if mode != GcMode::DryRun && !storage.is_dedicated() {
+        anyhow::bail!(
+            "Refusing to clean up a storage that is shared with other data, configure a prefix for it first"
+        );
+    }
+
+    let mut summary = GcSummary::default();
+
+    for StoredObject { name, modified_at } in storage.list().await? {
+        if name.starts_with(QUARANTINE_PREFIX) {
+            continue;
+        }
+
+        if referenced.contains(&name) {
+            summary.referenced += 1;
+            continue;
+        }
+
+        if modified_at > cutoff {
+            summary.recent += 1;
+            continue;
+        }
+
+        match mode {
+            GcMode::DryRun => {}
+            GcMode::Quarantine => {
+                let content = storage.get(&name).await?;
+                storage
+                    .put(&format!("{QUARANTINE_PREFIX}{name}"), content)
+                    .await?;
+                storage.delete(&name).await?;
+
+                tracing::info!("Quarantined unused screenshot {name}.");
+            }
+            GcMode::Delete => {
+                storage.delete(&name).await?;
+
+                tracing::info!("Deleted unused screenshot {name}.");
+            }
+        }
+
+        summary.orphaned.push(name);
+    }
+
+    Ok(summary)
+}
+
+static STORAGE: OnceLock<Box<dyn ScreenshotStorage>> = OnceLock::new();
+
 pub struct FileManager;
 
 impl FileManager {
+    pub fn set_storage(storage: Box<dyn ScreenshotStorage>) {
+        if STORAGE.set(storage).is_err() {
+            panic!("Failed to set screenshot storage!");
+        }
+    }
+
+    fn storage() -> &'static dyn ScreenshotStorage {
+        STORAGE
+            .get()
+            .expect("Screenshot storage should have been initialized by now!")
+            .as_ref()
+    }
+
     pub async fn get(path: &str) -> anyhow::Result<CreateAttachment> {
-        let file = File::open(format!("screenshots/{path}")).await?;
-        CreateAttachment::file(&file, path).await.context(format!(
+        let content = Self::storage().get(path).await.context(format!(
             "Failed to create attachment from file at path {path}"
-        ))
-    }
+        ))?;
 
-    pub async fn save(attachment: Attachment, user_id: UserId) -> anyhow::Result<String> {
-        let now = Utc::now();
-        let date = format!("{}-{}-{}", now.year(), now.month(), now.day());
+        Ok(CreateAttachment::bytes(content, path))
+    }
 
-        let file_ext = match get_file_extension(attachment.filename.to_string()) {
-            Some(ext) => ext,
-            None => anyhow::bail!(
-                "Cannot read file extension from filename {}",
-                attachment.filename
-            ),
-        };
+    /// Saves a screenshot, which has to be a png or jpeg image.
+    pub async fn save(db_pool: &PgPool, attachment: Attachment) -> anyhow::Result<String> {
+        Self::store(db_pool, attachment, &[FileType::Png, FileType::Jpeg]).await
+    }
 
-        if file_ext != "jpeg" && file_ext != "jpg" && file_ext != "png" {
-            anyhow::bail!("Expected file extensions `jpeg`, `jpg` or `png` but got {file_ext}")
-        }
+    /// Saves a file of evidence, which may also be a text log.
+    pub async fn save_evidence(db_pool: &PgPool, attachment: Attachment) -> anyhow::Result<String> {
+        let allowed = [FileType::Png, FileType::Jpeg, FileType::Text];
+        Self::store(db_pool, attachment, &allowed).await
+    }
 
-        if attachment.size >= 5_000_000 {
+    async fn store(
+        db_pool: &PgPool,
+        attachment: Attachment,
+        allowed: &[FileType],
+    ) -> anyhow::Result<String> {
+        if attachment.size >= MAX_FILE_SIZE {
             anyhow::bail!(
                 "File size too large. Max file size is 5MB, but got {} bytes",
                 attachment.size
@@ -41,28 +412,80 @@ impl FileManager {
         }
 
         let attachment_content = attachment.download().await?;
-        let file_name = format!("{date}_{}.{file_ext}", user_id);
-        let file_path = format!("screenshots/{}", &file_name);
 
-        tokio::fs::create_dir_all("screenshots").await?;
+        if attachment_content.len() != attachment.size as usize {
+            anyhow::bail!(
+                "Downloaded {} bytes of {}, but Discord reported {} bytes",
+                attachment_content.len(),
+                attachment.filename,
+                attachment.size
+            );
+        }
+
+        let stored_file = to_stored_file(&attachment_content, allowed)?;
 
-        let mut file = File::create(&file_path).await?;
-        file.write_all(&attachment_content).await?;
+        Self::storage()
+            .put(&stored_file.file_name, attachment_content)
+            .await?;
+        StoredFileModelController::create(db_pool, &stored_file).await?;
 
-        Ok(file_name)
+        Ok(stored_file.file_name)
     }
 
-    pub async fn delete(path: &str) -> anyhow::Result<()> {
-        remove_file(format!("screenshots/{path}"))
-            .await
-            .context(format!(
-                "Failed to delete screenshot {path} from the file system."
-            ))?;
+    /// Deletes a file unless a report still uses it, since identical uploads share a file.
+    pub async fn delete(db_pool: &PgPool, path: &str) -> anyhow::Result<()> {
+        if StoredFileModelController::is_referenced(db_pool, path).await? {
+            tracing::info!("Keeping screenshot {path} because a report still uses it.");
+            return Ok(());
+        }
+
+        Self::storage().delete(path).await?;
+        StoredFileModelController::delete(db_pool, path).await?;
 
-        tracing::info!("Deleted screenshot {path} from the file system.");
+        tracing::info!("Deleted screenshot {path}.");
 
         Ok(())
     }
+
+    /// Whether unused files may be quarantined or deleted, see [ScreenshotStorage::is_dedicated].
+    pub fn can_clean_up() -> bool {
+        Self::storage().is_dedicated()
+    }
+
+    /// Cleans up the screenshots and evidence no report uses that were last modified before the cutoff.
+    pub async fn collect_garbage(
+        db_pool: &PgPool,
+        cutoff: DateTime<Utc>,
+        mode: GcMode,
+    ) -> anyhow::Result<GcSummary> {
+        let referenced = StoredFileModelController::get_referenced_file_names(db_pool)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let summary = collect_garbage(Self::storage(), &referenced, cutoff, mode).await?;
+
+        if mode != GcMode::DryRun {
+            for file_name in &summary.orphaned {
+                StoredFileModelController::delete(db_pool, file_name).await?;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Verifies every recorded file against its recorded hash.
+    pub async fn verify(db_pool: &PgPool) -> anyhow::Result<VerifySummary> {
+        let files = StoredFileModelController::get_all(db_pool).await?;
+
+        verify_all(Self::storage(), &files).await
+    }
+}
+
+/// Whether a stored file can be shown as an image in an embed.
+pub fn is_image(file_name: &str) -> bool {
+    get_file_extension(file_name.to_string())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
 }
 
 fn get_file_extension(file_name: String) -> Option<String> {
@@ -73,3 +496,215 @@ fn get_file_extension(file_name: String) -> Option<String> {
         .last()
         .map(|last| last.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Content and modification time by name.
+    type MemoryFiles = HashMap<String, (Vec<u8>, DateTime<Utc>)>;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        files: Mutex<MemoryFiles>,
+        shared: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl ScreenshotStorage for MemoryStorage {
+        async fn put(&self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+            let file = (content, Utc::now());
+            self.files.lock().unwrap().insert(name.to_string(), file);
+            Ok(())
+        }
+
+        async fn get(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(name)
+                .map(|(content, _)| content.clone())
+                .context("No such screenshot")
+        }
+
+        async fn delete(&self, name: &str) -> anyhow::Result<()> {
+            self.files.lock().unwrap().remove(name);
+            Ok(())
+        }
+
+        async fn list(&self) -> anyhow::Result<Vec<StoredObject>> {
+            let files = self.files.lock().unwrap();
+
+            Ok(files
+                .iter()
+                .map(|(name, (_, modified_at))| StoredObject {
+                    name: name.clone(),
+                    modified_at: *modified_at,
+                })
+                .collect())
+        }
+
+        fn is_dedicated(&self) -> bool {
+            !self.shared
+        }
+    }
+
+    #[test]
+    fn copies_only_missing_screenshots() {
+        let from = MemoryStorage::default();
+        let to = MemoryStorage::default();
+
+        futures::executor::block_on(async {
+            from.put("a.png", vec![1]).await.unwrap();
+            from.put("b.png", vec![2]).await.unwrap();
+            to.put("a.png", vec![9]).await.unwrap();
+
+            let summary = copy_all(&from, &to).await.unwrap();
+            assert_eq!(
+                summary,
+                CopySummary {
+                    copied: 1,
+                    skipped: 1
+                }
+            );
+
+            assert_eq!(to.get("a.png").await.unwrap(), vec![9]);
+            assert_eq!(to.get("b.png").await.unwrap(), vec![2]);
+        });
+    }
+
+    #[test]
+    fn only_screenshots_are_images() {
+        assert!(is_image("2024-5-18_123.png"));
+        assert!(is_image("2024-5-18_123.jpeg"));
+        assert!(!is_image("0a1b.txt"));
+        assert!(!is_image("no_extension"));
+    }
+
+    #[test]
+    fn collects_only_old_unreferenced_screenshots() {
+        let storage = MemoryStorage::default();
+        let referenced = HashSet::from(["used.png".to_string()]);
+
+        futures::executor::block_on(async {
+            for name in ["used.png", "orphan.png", "quarantine/old.png"] {
+                storage.put(name, vec![1]).await.unwrap();
+            }
+
+            // everything was just written, so nothing is past the grace period yet
+            let cutoff = Utc::now() - chrono::Duration::hours(1);
+            let summary = collect_garbage(&storage, &referenced, cutoff, GcMode::Delete)
+                .await
+                .unwrap();
+            assert_eq!((summary.orphaned.len(), summary.recent), (0, 1));
+
+            let cutoff = Utc::now() + chrono::Duration::hours(1);
+            let summary = collect_garbage(&storage, &referenced, cutoff, GcMode::DryRun)
+                .await
+                .unwrap();
+            assert_eq!(summary.orphaned, vec!["orphan.png"]);
+            assert!(storage.get("orphan.png").await.is_ok());
+
+            let summary = collect_garbage(&storage, &referenced, cutoff, GcMode::Quarantine)
+                .await
+                .unwrap();
+            assert_eq!(summary.referenced, 1);
+            assert!(storage.get("orphan.png").await.is_err());
+            assert!(storage.get("quarantine/orphan.png").await.is_ok());
+        });
+    }
+
+    #[test]
+    fn sniffs_file_type_from_content() {
+        assert_eq!(
+            FileType::sniff(b"\x89PNG\r\n\x1a\n\0\0"),
+            Some(FileType::Png)
+        );
+        assert_eq!(
+            FileType::sniff(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(FileType::Jpeg)
+        );
+        assert_eq!(
+            FileType::sniff("[12:00] spam: free nitro".as_bytes()),
+            Some(FileType::Text)
+        );
+        assert_eq!(FileType::sniff(&[0x00, 0x61, 0x73, 0x6D]), None);
+        assert_eq!(FileType::sniff(&[]), None);
+    }
+
+    #[test]
+    fn names_files_after_their_content() {
+        let png = b"\x89PNG\r\n\x1a\nimage".to_vec();
+        let stored = to_stored_file(&png, &[FileType::Png]).unwrap();
+
+        assert_eq!(stored.file_name, format!("{}.png", sha256_hex(&png)));
+        assert_eq!(stored.mime_type, "image/png");
+        assert_eq!(stored.size, png.len() as i32);
+
+        // text is rejected where only images are allowed
+        assert!(to_stored_file(b"not an image", &[FileType::Png, FileType::Jpeg]).is_err());
+    }
+
+    #[test]
+    fn refuses_to_clean_up_shared_storage() {
+        let storage = MemoryStorage {
+            shared: true,
+            ..Default::default()
+        };
+        let cutoff = Utc::now() + chrono::Duration::hours(1);
+
+        futures::executor::block_on(async {
+            storage.put("unrelated.bin", vec![1]).await.unwrap();
+
+            for mode in [GcMode::Quarantine, GcMode::Delete] {
+                assert!(collect_garbage(&storage, &HashSet::new(), cutoff, mode)
+                    .await
+                    .is_err());
+            }
+            assert!(storage.get("unrelated.bin").await.is_ok());
+
+            let summary = collect_garbage(&storage, &HashSet::new(), cutoff, GcMode::DryRun)
+                .await
+                .unwrap();
+            assert_eq!(summary.orphaned, vec!["unrelated.bin"]);
+        });
+    }
+
+    #[test]
+    fn finds_missing_and_corrupted_files() {
+        let storage = MemoryStorage::default();
+        let record = |name: &str, content: &[u8]| StoredFile {
+            file_name: name.to_string(),
+            sha256: sha256_hex(content),
+            mime_type: "image/png".to_string(),
+            size: content.len() as i32,
+        };
+
+        futures::executor::block_on(async {
+            storage.put("ok.png", vec![1]).await.unwrap();
+            storage.put("corrupted.png", vec![3]).await.unwrap();
+            storage.put("2024-5-18_1.png", vec![5]).await.unwrap();
+            storage.put("quarantine/old.png", vec![6]).await.unwrap();
+
+            let files = [
+                record("ok.png", &[1]),
+                record("missing.png", &[2]),
+                record("corrupted.png", &[4]),
+            ];
+            let summary = verify_all(&storage, &files).await.unwrap();
+
+            assert_eq!(
+                summary,
+                VerifySummary {
+                    ok: 1,
+                    missing: vec!["missing.png".to_string()],
+                    corrupted: vec!["corrupted.png".to_string()],
+                    unrecorded: vec!["2024-5-18_1.png".to_string()],
+                }
+            );
+        });
+    }
+}